@@ -0,0 +1,63 @@
+// Throughput of `ShardedSpendStore` against `MemorySpendStore`, a single mutex
+// around one `HashSet`, with every thread redeeming its own distinct keys.
+//
+// Run with `cargo bench --bench spend_store`.
+
+use std::thread;
+use std::time::Instant;
+
+use hashcash::{MemorySpendStore, ShardedSpendStore, SpendStore};
+
+static KEYS_PER_THREAD: usize = 200_000;
+static SHARDS: usize = 64;
+
+// Inserts per second with `threads` threads, key generation isn't timed
+fn throughput(store: &(impl SpendStore + Sync), threads: usize) -> f64 {
+    let keys: Vec<Vec<String>> = (0..threads)
+        .map(|thread| {
+            (0..KEYS_PER_THREAD)
+                .map(|i| {
+                    format!(
+                        "1:20:260105093015:user{}@example.org::c2FsdA==:{}",
+                        thread, i
+                    )
+                })
+                .collect()
+        })
+        .collect();
+
+    let start = Instant::now();
+    thread::scope(|scope| {
+        for keys in &keys {
+            scope.spawn(move || {
+                for key in keys {
+                    assert!(store.try_insert(key));
+                }
+            });
+        }
+    });
+
+    (threads * KEYS_PER_THREAD) as f64 / start.elapsed().as_secs_f64()
+}
+
+fn main() {
+    let max_threads = thread::available_parallelism().map_or(4, usize::from);
+
+    println!(
+        "threads  single mutex  sharded ({} shards)  speedup",
+        SHARDS
+    );
+    let mut threads = 1;
+    while threads <= max_threads {
+        let single = throughput(&MemorySpendStore::new(), threads);
+        let sharded = throughput(&ShardedSpendStore::new(SHARDS), threads);
+        println!(
+            "{:>7}  {:>9.2}M/s  {:>16.2}M/s  {:>6.2}x",
+            threads,
+            single / 1e6,
+            sharded / 1e6,
+            sharded / single
+        );
+        threads *= 2;
+    }
+}
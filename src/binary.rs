@@ -0,0 +1,384 @@
+use std::sync::OnceLock;
+
+use chrono::{DateTime, FixedOffset};
+
+use crate::{
+    extension_algorithm, extension_rounds, is_valid_separator, Algorithm, CounterEncoding,
+    DatePrecision, FieldEncoding, FormatVersion, HashcashError, Stamp, StampFormat, Target,
+};
+
+// Compact binary layout for storing stamps in bulk:
+// version byte, bits byte, 8-byte big-endian unix timestamp, varint length-prefixed
+// resource, extension and salt followed by a varint counter.
+// v2 stamps add 4-byte big-endian nanoseconds after the timestamp and a
+// length-prefixed algorithm name before the extension.
+// The format settings that change the hashed text follow the timestamp: a flag
+// byte, the separator as a varint code point and the date offset in seconds as
+// a zigzag varint. Parsing-only settings such as the limits are not stored.
+// The colon-delimited text form stays the wire format used for hashing.
+impl Stamp {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let resource = self.resource.as_bytes();
+        let extension = self.extension.as_deref().unwrap_or_default().as_bytes();
+
+        let mut bytes = Vec::with_capacity(resource.len() + extension.len() + self.salt.len() + 24);
+        bytes.push(self.version as u8);
+        bytes.push(self.requested_bits);
+        bytes.extend_from_slice(&self.creation_date.timestamp().to_be_bytes());
+
+        let algorithm = self.algorithm.to_string();
+        let fields: &[&[u8]] = match self.version {
+            FormatVersion::V2 => {
+                let nanos = self.creation_date.timestamp_subsec_nanos();
+                bytes.extend_from_slice(&nanos.to_be_bytes());
+                &[resource, algorithm.as_bytes(), extension, &self.salt]
+            }
+            _ => &[resource, extension, &self.salt],
+        };
+
+        write_format(&mut bytes, &self.format);
+
+        for field in fields {
+            write_varint(&mut bytes, field.len() as u64);
+            bytes.extend_from_slice(field);
+        }
+
+        write_varint(&mut bytes, self.counter as u64);
+        bytes
+    }
+
+    /// Parses the layout written by `to_bytes`, an empty extension is read as `None`.
+    /// The stored format settings are restored, everything else of the format is
+    /// the default, whose limits apply to the fields.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, HashcashError> {
+        let mut reader = Reader { bytes };
+
+        let version = match reader.take(1)?[0] {
+            0 => FormatVersion::V0,
+            1 => FormatVersion::V1,
+            2 => FormatVersion::V2,
+            _ => return Err(HashcashError::InvalidVersion),
+        };
+
+        let requested_bits = reader.take(1)?[0];
+
+        let timestamp = i64::from_be_bytes(reader.take(8)?.try_into().unwrap());
+        let nanos = match version {
+            FormatVersion::V2 => u32::from_be_bytes(reader.take(4)?.try_into().unwrap()),
+            _ => 0,
+        };
+        let creation_date = match DateTime::from_timestamp(timestamp, nanos) {
+            Some(v) => v,
+            None => return Err(HashcashError::InvalidDate),
+        };
+
+        let format = read_format(&mut reader)?;
+
+        let resource = reader.take_prefixed()?;
+        if resource.len() > format.max_resource_len {
+            return Err(HashcashError::ResourceTooLong);
+        }
+
+        let resource = match String::from_utf8(resource.to_vec()) {
+            Ok(v) => v,
+            Err(_) => return Err(HashcashError::InvalidEncoding),
+        };
+
+        let algorithm = match version {
+            FormatVersion::V2 => match std::str::from_utf8(reader.take_prefixed()?) {
+                Ok(v) => Some(Algorithm::try_from(v)?),
+                Err(_) => return Err(HashcashError::InvalidEncoding),
+            },
+            _ => None,
+        };
+
+        let extension = reader.take_prefixed()?;
+        if extension.len() > format.limits.extension {
+            return Err(HashcashError::FieldTooLong);
+        }
+
+        let extension = match std::str::from_utf8(extension) {
+            Ok("") => None,
+            // v0 stamps have no extension field to write it to
+            Ok(_) if version == FormatVersion::V0 => return Err(HashcashError::InvalidEncoding),
+            Ok(v) => Some(v.to_string()),
+            Err(_) => return Err(HashcashError::InvalidEncoding),
+        };
+
+        let algorithm = match algorithm {
+            Some(v) => v,
+            None => extension_algorithm(extension.as_deref())?,
+        };
+        format.limits.check_bits(requested_bits, algorithm)?;
+        let rounds = extension_rounds(extension.as_deref())?;
+        if rounds > format.limits.rounds {
+            return Err(HashcashError::InvalidRounds);
+        }
+
+        let salt = reader.take_prefixed()?.to_vec();
+        if salt.len() > format.limits.salt {
+            return Err(HashcashError::FieldTooLong);
+        }
+
+        let counter = match usize::try_from(reader.varint()?) {
+            Ok(v) => v,
+            Err(_) => return Err(HashcashError::InvalidCounter),
+        };
+
+        if !reader.bytes.is_empty() {
+            return Err(HashcashError::InvalidEncoding);
+        }
+
+        Ok(Stamp {
+            version,
+            requested_bits,
+            creation_date,
+            resource,
+            extension,
+            salt,
+            counter,
+            algorithm,
+            rounds,
+            format,
+            target: Target::default(),
+            counter_step: 1,
+            digest_cache: OnceLock::new(),
+            dirty: false,
+            server_secret: Vec::new(),
+        })
+    }
+}
+
+fn write_format(bytes: &mut Vec<u8>, format: &StampFormat) {
+    let date_precision = match format.date_precision {
+        DatePrecision::Days => 0,
+        DatePrecision::Minutes => 1,
+        DatePrecision::Seconds => 2,
+    };
+    let field_encoding = match format.field_encoding {
+        FieldEncoding::Base64 => 0,
+        FieldEncoding::Base32 => 1,
+        FieldEncoding::Text => 2,
+    };
+    let counter_encoding = match format.counter_encoding {
+        CounterEncoding::Binary => 0,
+        CounterEncoding::Base64Digits => 1,
+    };
+
+    bytes.push(
+        u8::from(format.percent_encode_resource)
+            | u8::from(format.four_digit_year) << 1
+            | u8::from(format.hash_extension) << 2
+            | date_precision << 3
+            | field_encoding << 5
+            | counter_encoding << 7,
+    );
+
+    write_varint(bytes, u64::from(u32::from(format.separator)));
+    let offset = format.date_offset.local_minus_utc();
+    write_varint(bytes, u64::from(((offset << 1) ^ (offset >> 31)) as u32));
+}
+
+fn read_format(reader: &mut Reader) -> Result<StampFormat, HashcashError> {
+    let flags = reader.take(1)?[0];
+    let date_precision = match flags >> 3 & 0b11 {
+        0 => DatePrecision::Days,
+        1 => DatePrecision::Minutes,
+        2 => DatePrecision::Seconds,
+        _ => return Err(HashcashError::InvalidEncoding),
+    };
+    let field_encoding = match flags >> 5 & 0b11 {
+        0 => FieldEncoding::Base64,
+        1 => FieldEncoding::Base32,
+        2 => FieldEncoding::Text,
+        _ => return Err(HashcashError::InvalidEncoding),
+    };
+    let counter_encoding = match flags >> 7 {
+        0 => CounterEncoding::Binary,
+        _ => CounterEncoding::Base64Digits,
+    };
+
+    let separator = match u32::try_from(reader.varint()?)
+        .ok()
+        .and_then(char::from_u32)
+    {
+        Some(v) => v,
+        None => return Err(HashcashError::InvalidEncoding),
+    };
+    if !is_valid_separator(separator) {
+        return Err(HashcashError::InvalidSeparator);
+    }
+
+    let offset = match u32::try_from(reader.varint()?) {
+        Ok(v) => (v >> 1) as i32 ^ -((v & 1) as i32),
+        Err(_) => return Err(HashcashError::InvalidEncoding),
+    };
+    let date_offset = match FixedOffset::east_opt(offset) {
+        Some(v) => v,
+        None => return Err(HashcashError::InvalidDate),
+    };
+
+    Ok(StampFormat {
+        separator,
+        percent_encode_resource: flags & 1 != 0,
+        four_digit_year: flags & 0b10 != 0,
+        hash_extension: flags & 0b100 != 0,
+        date_precision,
+        field_encoding,
+        counter_encoding,
+        date_offset,
+        ..StampFormat::default()
+    })
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+struct Reader<'b> {
+    bytes: &'b [u8],
+}
+
+impl<'b> Reader<'b> {
+    fn take(&mut self, len: usize) -> Result<&'b [u8], HashcashError> {
+        if self.bytes.len() < len {
+            return Err(HashcashError::InvalidEncoding);
+        }
+
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn take_prefixed(&mut self) -> Result<&'b [u8], HashcashError> {
+        let len = self.varint()?;
+        match usize::try_from(len) {
+            Ok(len) => self.take(len),
+            Err(_) => Err(HashcashError::InvalidEncoding),
+        }
+    }
+
+    fn varint(&mut self) -> Result<u64, HashcashError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(HashcashError::InvalidEncoding)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, FixedOffset};
+
+    use crate::{
+        Algorithm, DatePrecision, FieldEncoding, FormatVersion, HashcashError, Stamp, StampFormat,
+        DEFAULT_EXPIRY,
+    };
+
+    // Every stamp keeps its text form, and therefore its proof, through the
+    // binary layout
+    #[test]
+    fn round_trips_non_default_formats() {
+        let formats = [
+            StampFormat {
+                separator: '|',
+                ..StampFormat::default()
+            },
+            StampFormat {
+                four_digit_year: true,
+                ..StampFormat::default()
+            },
+            StampFormat {
+                date_precision: DatePrecision::Minutes,
+                ..StampFormat::default()
+            },
+            StampFormat {
+                field_encoding: FieldEncoding::Base32,
+                ..StampFormat::default()
+            },
+            StampFormat {
+                percent_encode_resource: true,
+                ..StampFormat::default()
+            },
+            StampFormat {
+                hash_extension: false,
+                ..StampFormat::default()
+            },
+            StampFormat {
+                date_offset: FixedOffset::west_opt(5 * 3600).unwrap(),
+                ..StampFormat::default()
+            },
+            StampFormat::reference(),
+        ];
+
+        for version in [FormatVersion::V1, FormatVersion::V2] {
+            for format in formats {
+                let stamp = Stamp::new(version, 8, "foo bar@example.org".to_string(), None)
+                    .with_format(format)
+                    .mint();
+                let parsed = Stamp::from_bytes(&stamp.to_bytes()).unwrap();
+
+                assert_eq!(parsed.to_string(), stamp.to_string());
+                assert!(parsed
+                    .check_bits("foo bar@example.org", &DEFAULT_EXPIRY, 8)
+                    .is_ok());
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_v0_records_with_an_extension() {
+        let v0 = Stamp::new(FormatVersion::V0, 8, "r".to_string(), None).mint();
+        assert!(Stamp::from_bytes(&v0.to_bytes()).is_ok());
+
+        // v0 and v1 records share their layout but for the version byte
+        let v1 = Stamp::new(FormatVersion::V1, 8, "r".to_string(), None)
+            .with_algorithm(Algorithm::Sha256)
+            .with_rounds(2)
+            .mint();
+        let mut bytes = v1.to_bytes();
+        bytes[0] = FormatVersion::V0 as u8;
+        assert_eq!(
+            Stamp::from_bytes(&bytes).err(),
+            Some(HashcashError::InvalidEncoding)
+        );
+    }
+
+    #[test]
+    fn round_trips_parsed_reference_stamps() {
+        let line = "1:20:060408:adam@cypherspace.org::1QTjaYd7niiQA/sc:ePa";
+        let stamp = Stamp::parse_with_format(line, StampFormat::reference()).unwrap();
+        let parsed = Stamp::from_bytes(&stamp.to_bytes()).unwrap();
+
+        assert_eq!(parsed.to_string(), line);
+        assert!(parsed.check_no_expiry("adam@cypherspace.org", 20).is_ok());
+        assert!(parsed
+            .check_bits("adam@cypherspace.org", &Duration::days(1), 20)
+            .is_err());
+    }
+
+    #[test]
+    fn enforces_field_limits() {
+        let limits = StampFormat::default().limits;
+
+        let extension = Some("x".repeat(limits.extension + 1));
+        let stamp = Stamp::new(FormatVersion::V1, 1, "r".to_string(), extension);
+        let parsed = Stamp::from_bytes(&stamp.to_bytes());
+        assert!(matches!(parsed, Err(HashcashError::FieldTooLong)));
+
+        let stamp = Stamp::new(FormatVersion::V1, 1, "r".to_string(), None)
+            .with_salt(vec![0; limits.salt + 1])
+            .unwrap();
+        let parsed = Stamp::from_bytes(&stamp.to_bytes());
+        assert!(matches!(parsed, Err(HashcashError::FieldTooLong)));
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use chrono::Duration;
+
+use crate::{Algorithm, HashcashError, Stamp, Target};
+
+// Remembers the proof checks of recently seen stamps, so a stamp arriving
+// again skips the hashing. Purely a performance optimization: resource and
+// expiry are still checked every time and replay rejection is up to a
+// `SpendStore`, which only runs after the check.
+pub struct VerificationCache {
+    capacity: usize,
+    state: Mutex<CacheState>,
+}
+
+// everything the proof depends on and the target, neither the algorithm and
+// rounds nor the target have to be part of the hashed payload
+type CacheKey = ((Vec<u8>, Algorithm, u32), Target, u8);
+
+#[derive(Default)]
+struct CacheState {
+    // result and the tick it was last used at
+    entries: HashMap<CacheKey, (Result<(), HashcashError>, u64)>,
+    // keys by the tick they were last used at, the first one is evicted first
+    recency: BTreeMap<u64, CacheKey>,
+    tick: u64,
+}
+
+impl CacheState {
+    fn touch(&mut self, key: &CacheKey) -> Option<Result<(), HashcashError>> {
+        self.tick += 1;
+        let tick = self.tick;
+
+        let (result, last_used) = self.entries.get_mut(key)?;
+        let previous = std::mem::replace(last_used, tick);
+        let result = result.clone();
+
+        if let Some(key) = self.recency.remove(&previous) {
+            self.recency.insert(tick, key);
+        }
+        Some(result)
+    }
+}
+
+impl VerificationCache {
+    pub fn new(capacity: usize) -> Self {
+        VerificationCache {
+            capacity: capacity.max(1),
+            state: Mutex::default(),
+        }
+    }
+
+    fn get_or_check(&self, stamp: &Stamp, bits: u8) -> Result<(), HashcashError> {
+        // a changed stamp may share its bytes with a valid one
+        if stamp.dirty {
+            return Err(HashcashError::StaleProof);
+        }
+
+        let key = (stamp.proof_inputs(), stamp.target, bits);
+        if let Some(result) = self.state.lock().unwrap().touch(&key) {
+            return result;
+        }
+
+        // hashing doesn't hold up other checks, a stamp checked by two threads
+        // at once is just hashed twice
+        let result = stamp.validate_bits(bits);
+
+        let mut state = self.state.lock().unwrap();
+        if state.touch(&key).is_some() {
+            return result;
+        }
+
+        while state.entries.len() >= self.capacity {
+            match state.recency.pop_first() {
+                Some((_, oldest)) => state.entries.remove(&oldest),
+                None => break,
+            };
+        }
+
+        let tick = state.tick;
+        state.recency.insert(tick, key.clone());
+        state.entries.insert(key, (result.clone(), tick));
+
+        result
+    }
+}
+
+impl Stamp {
+    /// Like `check_bits` with the proof check answered from `cache` for stamps it has
+    /// seen at the same difficulty.
+    pub fn check_cached(
+        &self,
+        resource: &str,
+        expiry_duration: &Duration,
+        required_bits: u8,
+        cache: &VerificationCache,
+    ) -> Result<&Self, HashcashError> {
+        if !self.resource_matches(resource) {
+            return Err(HashcashError::ResourceMismatch);
+        }
+
+        if self.is_expired(expiry_duration) {
+            return Err(HashcashError::Expired);
+        }
+
+        cache.get_or_check(self, required_bits)?;
+
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FormatVersion, StampFormat, DEFAULT_EXPIRY};
+
+    fn cached_resources(cache: &VerificationCache) -> Vec<String> {
+        let state = cache.state.lock().unwrap();
+        state
+            .recency
+            .values()
+            .map(|((payload, ..), ..)| String::from_utf8(payload.clone()).unwrap())
+            .map(|payload| payload.split(':').nth(3).unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used_stamp() {
+        let cache = VerificationCache::new(2);
+        let stamps: Vec<Stamp> = ["a", "b", "c"]
+            .iter()
+            .map(|resource| Stamp::new(FormatVersion::V1, 4, resource.to_string(), None).mint())
+            .collect();
+
+        for stamp in &stamps[..2] {
+            assert!(stamp
+                .check_cached(&stamp.resource, &DEFAULT_EXPIRY, 4, &cache)
+                .is_ok());
+        }
+        assert!(stamps[0]
+            .check_cached("a", &DEFAULT_EXPIRY, 4, &cache)
+            .is_ok());
+        assert!(stamps[2]
+            .check_cached("c", &DEFAULT_EXPIRY, 4, &cache)
+            .is_ok());
+
+        assert_eq!(cached_resources(&cache), ["a", "c"]);
+        assert_eq!(cache.state.lock().unwrap().entries.len(), 2);
+    }
+
+    #[test]
+    fn remembers_failed_checks() {
+        let cache = VerificationCache::new(4);
+        let stamp = Stamp::new(FormatVersion::V1, 32, "a".to_string(), None);
+
+        for _ in 0..2 {
+            let result = stamp.check_cached("a", &DEFAULT_EXPIRY, 32, &cache);
+            assert!(matches!(result, Err(HashcashError::InsufficientBits)));
+        }
+        assert_eq!(cached_resources(&cache), ["a"]);
+    }
+
+    #[test]
+    fn unhashed_rounds_are_part_of_the_key() {
+        let format = StampFormat {
+            hash_extension: false,
+            ..StampFormat::default()
+        };
+        let cache = VerificationCache::new(4);
+        let minted = Stamp::new(FormatVersion::V1, 16, "a".to_string(), None)
+            .with_format(format)
+            .mint();
+        assert!(minted
+            .check_cached("a", &DEFAULT_EXPIRY, 16, &cache)
+            .is_ok());
+
+        // same hashed bytes, but the digest is hashed ten times
+        let wire = minted.to_string().replacen(":a::", ":a:rounds=10:", 1);
+        let rounds = Stamp::parse_with_format(&wire, format).unwrap();
+        assert_eq!(rounds.hashed_payload(), minted.hashed_payload());
+        assert_eq!(
+            rounds.check_cached("a", &DEFAULT_EXPIRY, 16, &cache).err(),
+            Some(HashcashError::InsufficientBits)
+        );
+    }
+
+    #[test]
+    fn required_bits_are_part_of_the_key() {
+        let cache = VerificationCache::new(4);
+        let stamp = Stamp::new(FormatVersion::V1, 8, "a".to_string(), None).mint();
+
+        assert!(stamp.check_cached("a", &DEFAULT_EXPIRY, 8, &cache).is_ok());
+        assert_eq!(
+            stamp.check_cached("a", &DEFAULT_EXPIRY, 40, &cache).err(),
+            Some(HashcashError::InsufficientBits)
+        );
+    }
+}
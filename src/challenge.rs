@@ -0,0 +1,53 @@
+use std::ops::Deref;
+
+use chrono::Duration;
+
+use crate::{HashcashError, Stamp};
+
+// What a server asks a client to mint for, e.g. for a proof-of-work gated endpoint
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Challenge {
+    pub resource: String,
+    pub bits: u8,
+    pub expiry: Duration,
+}
+
+impl Challenge {
+    pub fn new(resource: String, bits: u8, expiry: Duration) -> Self {
+        Challenge {
+            resource,
+            bits,
+            expiry,
+        }
+    }
+}
+
+// A stamp that passed `Stamp::from_challenge_response`, only that can build one
+pub struct VerifiedStamp(Stamp);
+
+impl VerifiedStamp {
+    pub fn into_inner(self) -> Stamp {
+        self.0
+    }
+}
+
+impl Deref for VerifiedStamp {
+    type Target = Stamp;
+
+    fn deref(&self) -> &Stamp {
+        &self.0
+    }
+}
+
+impl Stamp {
+    /// Parses the stamp a client returned for `challenge` and checks it against the
+    /// challenge's resource, expiry and bits. The bits the stamp claims don't matter.
+    pub fn from_challenge_response(
+        challenge: &Challenge,
+        response: &str,
+    ) -> Result<VerifiedStamp, HashcashError> {
+        let stamp = Stamp::try_from(response.trim().to_string())?;
+        stamp.check_bits(&challenge.resource, &challenge.expiry, challenge.bits)?;
+        Ok(VerifiedStamp(stamp))
+    }
+}
@@ -0,0 +1,219 @@
+use std::str::FromStr;
+use std::time::Instant;
+
+use chrono::Duration;
+
+use crate::{
+    Algorithm, FieldLimits, FormatVersion, HashcashError, MintOutcome, Stamp, Target, DEFAULT_BITS,
+};
+
+// Benchmark of `Stamp::calibrated_bits`, attempts per chunk and total time
+const CALIBRATION_CHUNK: usize = 1024;
+const CALIBRATION_TIME: std::time::Duration = std::time::Duration::from_millis(50);
+
+// A difficulty written the way people think about it, parsed into bits:
+//
+// - `fast`, `normal` or `slow` for 16, `DEFAULT_BITS` and 24 bits
+// - `<n>` or `<n>bits` for an exact bit count
+// - `~<time>@<rate>` for the bits a client at that hashrate solves within the
+//   time, see `max_bits_for`. Time is a number with `ms`, `s`, `m` or `h`, rate a
+//   number with an optional `k`, `M` or `G` prefix followed by `hps`, e.g.
+//   `~500ms@1Mhps`. The rate is an assumption about the client, nothing measures it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Difficulty {
+    pub bits: u8,
+}
+
+impl FromStr for Difficulty {
+    type Err = HashcashError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let bits = match value.trim() {
+            "fast" => 16,
+            "normal" => DEFAULT_BITS,
+            "slow" => 24,
+            value => match value.strip_prefix('~') {
+                Some(effort) => {
+                    let (time, rate) = effort.split_once('@').ok_or(HashcashError::InvalidBits)?;
+                    max_bits_for(parse_rate(rate)?, parse_time(time)?)
+                }
+                None => {
+                    let bits = value.strip_suffix("bits").unwrap_or(value);
+                    bits.parse().map_err(|_| HashcashError::InvalidBits)?
+                }
+            },
+        };
+
+        Ok(Difficulty { bits })
+    }
+}
+
+fn parse_time(value: &str) -> Result<Duration, HashcashError> {
+    let (number, unit_ms) = if let Some(number) = value.strip_suffix("ms") {
+        (number, 1.0)
+    } else if let Some(number) = value.strip_suffix('s') {
+        (number, 1000.0)
+    } else if let Some(number) = value.strip_suffix('m') {
+        (number, 60_000.0)
+    } else if let Some(number) = value.strip_suffix('h') {
+        (number, 3_600_000.0)
+    } else {
+        return Err(HashcashError::InvalidBits);
+    };
+
+    match number.parse::<f64>() {
+        Ok(v) if v >= 0.0 => Ok(Duration::milliseconds((v * unit_ms) as i64)),
+        _ => Err(HashcashError::InvalidBits),
+    }
+}
+
+fn parse_rate(value: &str) -> Result<f64, HashcashError> {
+    let value = value
+        .strip_suffix("hps")
+        .ok_or(HashcashError::InvalidBits)?;
+    let (number, factor) = match value.chars().last() {
+        Some('k') => (&value[..value.len() - 1], 1e3),
+        Some('M') => (&value[..value.len() - 1], 1e6),
+        Some('G') => (&value[..value.len() - 1], 1e9),
+        _ => (value, 1.0),
+    };
+
+    match number.parse::<f64>() {
+        Ok(v) if v >= 0.0 => Ok(v * factor),
+        _ => Err(HashcashError::InvalidBits),
+    }
+}
+
+/// Highest difficulty a client hashing at `hashrate` attempts per second can
+/// expect to solve within `budget`, `floor(log2(hashrate * seconds))`.
+///
+/// Use it as a cap on issued difficulty so slow honest clients aren't locked out.
+pub fn max_bits_for(hashrate: f64, budget: Duration) -> u8 {
+    let attempts = hashrate * budget.num_milliseconds() as f64 / 1000.0;
+    if attempts < 1.0 || attempts.is_nan() {
+        return 0;
+    }
+
+    attempts.log2().floor().min(f64::from(u8::MAX)) as u8
+}
+
+/// Attempts a mint needs on average for `bits` leading zero bits, `2^bits`
+/// saturating at `u64::MAX`.
+pub fn expected_iterations(bits: u8) -> u64 {
+    1u64.checked_shl(u32::from(bits)).unwrap_or(u64::MAX)
+}
+
+// Attempts per mint from `Stamp::difficulty_distribution_sample`, scaled to the
+// modelled difficulty
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MintCostSample {
+    pub min: u64,
+    pub median: u64,
+    pub max: u64,
+    pub mean: f64,
+}
+
+impl Stamp {
+    /// Accepts `1..=` the digest length of `algorithm` in bits.
+    ///
+    /// Parsing and checking go through `FieldLimits::check_bits`, which adds the
+    /// format's cap. `try_new` rejects infeasible bits up front, while `new` can't
+    /// fail and leaves it to `check`.
+    pub fn feasible_bits(bits: u8, algorithm: Algorithm) -> Result<(), HashcashError> {
+        FieldLimits::default().check_bits(bits, algorithm)
+    }
+
+    /// Difficulty a client can expect to solve within `target`, from a short
+    /// benchmark of this machine's hashrate divided by `safety_margin`.
+    ///
+    /// Client hardware varies and the benchmark only measures the server, so the
+    /// margin is a heuristic: 4.0 assumes clients four times slower, which costs
+    /// 2 bits. Takes about 50ms.
+    pub fn calibrated_bits(target: Duration, safety_margin: f64) -> u8 {
+        // a target no digest meets, so every chunk runs to the end
+        let mut stamp = Stamp::new(FormatVersion::V1, DEFAULT_BITS, String::new(), None)
+            .with_target(Target::Threshold([0; 20]));
+
+        let start = Instant::now();
+        let mut attempts = 0;
+        while start.elapsed() < CALIBRATION_TIME {
+            stamp = match stamp.mint_with(Some(CALIBRATION_CHUNK), None) {
+                MintOutcome::Solved(stamp)
+                | MintOutcome::Cancelled(stamp)
+                | MintOutcome::Exhausted(stamp) => stamp,
+            };
+            attempts += CALIBRATION_CHUNK;
+        }
+
+        let hashrate = attempts as f64 / start.elapsed().as_secs_f64();
+        max_bits_for(hashrate / safety_margin.max(1.0), target)
+    }
+
+    /// Models the attempts minting at `bits` takes: mints `trials` real stamps at
+    /// `sample_bits` (at most `bits`) and scales their attempts by the missing
+    /// `2^(bits - sample_bits)`. Shows the spread around the `2^bits` mean, an
+    /// unlucky mint can take several times as long.
+    pub fn difficulty_distribution_sample(
+        bits: u8,
+        trials: usize,
+        sample_bits: u8,
+    ) -> MintCostSample {
+        let sample_bits = sample_bits.min(bits);
+        let scale = expected_iterations(bits - sample_bits);
+
+        let mut attempts: Vec<u64> = (0..trials)
+            .map(|_| {
+                let stamp = Stamp::new(FormatVersion::V1, sample_bits, String::new(), None).mint();
+                (stamp.counter as u64 + 1).saturating_mul(scale)
+            })
+            .collect();
+        if attempts.is_empty() {
+            return MintCostSample::default();
+        }
+        attempts.sort_unstable();
+
+        MintCostSample {
+            min: attempts[0],
+            median: attempts[attempts.len() / 2],
+            max: attempts[attempts.len() - 1],
+            mean: attempts.iter().map(|v| *v as f64).sum::<f64>() / attempts.len() as f64,
+        }
+    }
+
+    /// Rough time minting took at `hashrate` attempts per second, `counter / hashrate`.
+    ///
+    /// Only an estimate: the actual time depends on the client's true hashrate, and
+    /// stamps minted from a random or stepped counter didn't try every counter
+    /// below theirs. A rate that isn't positive yields zero.
+    pub fn elapsed_mint_estimate(&self, hashrate: f64) -> Duration {
+        if hashrate.is_nan() || hashrate <= 0.0 {
+            return Duration::zero();
+        }
+
+        let millis = self.counter as f64 / hashrate * 1000.0;
+        Duration::try_milliseconds(millis.min(i64::MAX as f64) as i64)
+            .unwrap_or(Duration::max_value())
+    }
+
+    /// Like `check_bits` but also rejects counters beyond `factor` times the expected
+    /// iterations for the claimed bits, which hint at crafted stamps.
+    ///
+    /// This is a heuristic: honest but unlucky mints and stamps minted from a
+    /// random or stepped counter can fail it, so pick a generous factor.
+    pub fn check_plausible(
+        &self,
+        resource: &str,
+        expiry_duration: &Duration,
+        required_bits: u8,
+        factor: u64,
+    ) -> Result<&Self, HashcashError> {
+        self.check_bits(resource, expiry_duration, required_bits)?;
+
+        let limit = expected_iterations(self.requested_bits).saturating_mul(factor);
+        if self.counter as u64 > limit {
+            return Err(HashcashError::ImplausibleCounter);
+        }
+
+        Ok(self)
+    }
+}
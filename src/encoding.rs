@@ -0,0 +1,195 @@
+use std::io::{self, Write};
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+
+// RFC 4648 base32 alphabet
+static BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+// Bytes base64-encoded per write, a multiple of 3 so only the last chunk is padded
+const BASE64_CHUNK: usize = 48;
+
+// Standard base64 alphabet, used as digits by `CounterEncoding::Base64Digits`
+static BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// Encoding of the salt and counter fields
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FieldEncoding {
+    // Standard padded base64 as used by hashcash
+    #[default]
+    Base64,
+
+    // Unpadded RFC 4648 base32, decoded case-insensitively. For transports that
+    // mangle case or `+` and `/`, such as DNS records.
+    Base32,
+
+    // The field's characters are the value, as in stamps of the python module and
+    // the C tool whose salts are random base64 characters. Values that aren't
+    // made of base64 characters and `=` are written as base64.
+    Text,
+}
+
+// Encoding of the counter field
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CounterEncoding {
+    // The counter's binary digits in the field encoding
+    #[default]
+    Binary,
+
+    // The counter as a bijective base-64 numeral over the base64 alphabet, `A` is
+    // 0 and `/` 63, `BA` 64. Every string of base64 characters up to 10 long is a
+    // counter, so counters of the python module (hex) and the C tool (base64
+    // characters) are kept exactly as written.
+    Base64Digits,
+}
+
+impl FieldEncoding {
+    pub(crate) fn encode(self, data: &[u8]) -> String {
+        let mut encoded = Vec::new();
+        self.encode_into(data, &mut encoded).unwrap();
+        String::from_utf8(encoded).unwrap()
+    }
+
+    // Writes the encoded data, which is always ASCII, without allocating
+    pub(crate) fn encode_into(self, data: &[u8], out: &mut impl Write) -> io::Result<()> {
+        match self {
+            FieldEncoding::Base32 => base32_encode_into(data, out),
+            FieldEncoding::Text if is_text(data) => out.write_all(data),
+            FieldEncoding::Base64 | FieldEncoding::Text => {
+                let mut encoded = [0u8; BASE64_CHUNK / 3 * 4];
+                for chunk in data.chunks(BASE64_CHUNK) {
+                    let len = STANDARD.encode_slice(chunk, &mut encoded).unwrap();
+                    out.write_all(&encoded[..len])?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    pub(crate) fn decode(self, value: &str) -> Option<Vec<u8>> {
+        match self {
+            FieldEncoding::Base64 => STANDARD.decode(value).ok(),
+            FieldEncoding::Base32 => base32_decode(value),
+            FieldEncoding::Text => is_text(value.as_bytes()).then(|| value.as_bytes().to_vec()),
+        }
+    }
+}
+
+impl CounterEncoding {
+    pub(crate) fn encode(self, counter: usize, field_encoding: FieldEncoding) -> String {
+        let mut encoded = Vec::new();
+        self.encode_into(counter, field_encoding, &mut encoded)
+            .unwrap();
+        String::from_utf8(encoded).unwrap()
+    }
+
+    // Writes the encoded counter without allocating
+    pub(crate) fn encode_into(
+        self,
+        counter: usize,
+        field_encoding: FieldEncoding,
+        out: &mut impl Write,
+    ) -> io::Result<()> {
+        match self {
+            CounterEncoding::Binary => {
+                let mut digits = [0u8; usize::BITS as usize];
+                let mut start = digits.len();
+                let mut value = counter;
+                loop {
+                    start -= 1;
+                    digits[start] = b'0' + (value % 2) as u8;
+                    value /= 2;
+                    if value == 0 {
+                        break;
+                    }
+                }
+                field_encoding.encode_into(&digits[start..], out)
+            }
+            CounterEncoding::Base64Digits => {
+                // one digit per 6 bits and one more for the bijective offset
+                let mut digits = [0u8; usize::BITS as usize / 6 + 2];
+                let mut start = digits.len();
+                let mut value = counter as u128 + 1;
+                while value > 0 {
+                    value -= 1;
+                    start -= 1;
+                    digits[start] = BASE64_ALPHABET[(value % 64) as usize];
+                    value /= 64;
+                }
+                out.write_all(&digits[start..])
+            }
+        }
+    }
+
+    pub(crate) fn decode(self, value: &str, field_encoding: FieldEncoding) -> Option<usize> {
+        match self {
+            CounterEncoding::Binary => {
+                let digits = String::from_utf8(field_encoding.decode(value)?).ok()?;
+                usize::from_str_radix(&digits, 2).ok()
+            }
+            CounterEncoding::Base64Digits => {
+                if value.is_empty() {
+                    return None;
+                }
+
+                let mut counter = 0usize;
+                for c in value.bytes() {
+                    let digit = BASE64_ALPHABET.iter().position(|letter| *letter == c)? + 1;
+                    counter = counter.checked_mul(64)?.checked_add(digit)?;
+                }
+                Some(counter - 1)
+            }
+        }
+    }
+}
+
+fn is_text(data: &[u8]) -> bool {
+    !data.is_empty()
+        && data
+            .iter()
+            .all(|byte| byte.is_ascii_alphanumeric() || matches!(byte, b'+' | b'/' | b'='))
+}
+
+fn base32_encode_into(data: &[u8], out: &mut impl Write) -> io::Result<()> {
+    let mut buffer = 0u16;
+    let mut bits = 0;
+
+    for byte in data {
+        buffer = (buffer << 8) | u16::from(*byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.write_all(&[BASE32_ALPHABET[usize::from((buffer >> bits) & 0x1f)]])?;
+        }
+    }
+
+    if bits > 0 {
+        out.write_all(&[BASE32_ALPHABET[usize::from((buffer << (5 - bits)) & 0x1f)]])?;
+    }
+    Ok(())
+}
+
+fn base32_decode(value: &str) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(value.len() * 5 / 8);
+    let mut buffer = 0u16;
+    let mut bits = 0;
+
+    for c in value.bytes() {
+        let index = BASE32_ALPHABET
+            .iter()
+            .position(|letter| *letter == c.to_ascii_uppercase())?;
+        buffer = (buffer << 5) | index as u16;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+        }
+    }
+
+    // leftover bits are padding and have to be zero
+    if bits >= 5 || buffer & ((1 << bits) - 1) != 0 {
+        return None;
+    }
+
+    Some(decoded)
+}
@@ -0,0 +1,107 @@
+use std::fmt::{self, Display, Formatter};
+use std::io;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HashcashError {
+    // Parsing
+    MissingFields,
+    TooManyFields,
+    InvalidVersion,
+    InvalidBits,
+    InvalidDate,
+    InvalidAlgorithm,
+    InvalidRounds,
+    InvalidSalt,
+    InvalidCounter,
+    NonCanonicalCounter,
+    InvalidEncoding,
+    InvalidSeparator,
+    ResourceTooLong,
+    FieldTooLong,
+
+    // Building
+    InsufficientSalt,
+    InvalidTarget,
+    InvalidResource,
+    InvalidExtension,
+
+    // Checking
+    ResourceMismatch,
+    Expired,
+    InsufficientBits,
+    AlgorithmMismatch,
+    StaleProof,
+    ImplausibleCounter,
+    VersionNotAllowed,
+    AlreadySpent,
+    NoPolicy,
+    Rejected,
+
+    // Reading stamps
+    Io(io::ErrorKind),
+}
+
+impl Display for HashcashError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            HashcashError::MissingFields => write!(f, "Stamp is missing required fields"),
+            HashcashError::TooManyFields => {
+                write!(f, "Stamp has more fields than its version allows")
+            }
+            HashcashError::InvalidVersion => write!(f, "Invalid version"),
+            HashcashError::InvalidBits => write!(f, "Invalid requested bits"),
+            HashcashError::InvalidDate => write!(f, "Invalid creation date"),
+            HashcashError::InvalidAlgorithm => write!(f, "Invalid algorithm"),
+            HashcashError::InvalidRounds => write!(f, "Invalid hash rounds"),
+            HashcashError::InvalidSalt => write!(f, "Invalid salt"),
+            HashcashError::InvalidCounter => write!(f, "Invalid counter"),
+            HashcashError::NonCanonicalCounter => {
+                write!(f, "Counter is not in its minimal encoding")
+            }
+            HashcashError::InvalidEncoding => write!(f, "Invalid stamp encoding"),
+            HashcashError::InvalidSeparator => {
+                write!(f, "Separator can occur inside the stamp fields")
+            }
+            HashcashError::ResourceTooLong => write!(f, "Resource is too long"),
+            HashcashError::FieldTooLong => write!(f, "Stamp field is too long"),
+            HashcashError::InsufficientSalt => write!(f, "Salt is too short"),
+            HashcashError::InvalidTarget => write!(f, "Invalid difficulty target"),
+            HashcashError::InvalidResource => write!(f, "Invalid resource"),
+            HashcashError::InvalidExtension => write!(f, "Invalid extension"),
+            HashcashError::ResourceMismatch => {
+                write!(f, "The stamp resource doesn't match the expected resource.")
+            }
+            HashcashError::Expired => write!(f, "Stamp has expired."),
+            HashcashError::InsufficientBits => {
+                write!(
+                    f,
+                    "Stamp isn't producing hash with expected zero bit count."
+                )
+            }
+            HashcashError::AlgorithmMismatch => {
+                write!(f, "Stamp was minted with a different hash algorithm.")
+            }
+            HashcashError::StaleProof => {
+                write!(
+                    f,
+                    "Stamp was changed after minting and has to be minted again."
+                )
+            }
+            HashcashError::ImplausibleCounter => {
+                write!(
+                    f,
+                    "Stamp counter is far beyond what its difficulty requires."
+                )
+            }
+            HashcashError::VersionNotAllowed => {
+                write!(f, "Stamp format version is not accepted.")
+            }
+            HashcashError::AlreadySpent => write!(f, "Stamp has already been spent."),
+            HashcashError::NoPolicy => write!(f, "No policy matches the stamp resource."),
+            HashcashError::Rejected => write!(f, "Stamp was rejected by the verification policy."),
+            HashcashError::Io(kind) => write!(f, "I/O error: {}", kind),
+        }
+    }
+}
+
+impl std::error::Error for HashcashError {}
@@ -0,0 +1,256 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+
+use crate::{HashcashError, Stamp};
+
+// How long stamps stay valid, with an optional grace period on top for stamps
+// delayed in transport, e.g. mail sitting in a queue
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExpiryPolicy {
+    expiry: Duration,
+    // added per claimed bit, so harder stamps live longer
+    per_bit: Duration,
+    grace: Duration,
+}
+
+// How a stamp passed `Stamp::check_policy`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Acceptance {
+    Valid,
+    // expired, but not by more than the grace period
+    WithinGrace,
+}
+
+impl ExpiryPolicy {
+    pub fn new(expiry: Duration) -> Self {
+        ExpiryPolicy {
+            expiry,
+            per_bit: Duration::zero(),
+            grace: Duration::zero(),
+        }
+    }
+
+    /// Expiry of `base + per_bit * bits`, rewarding more work with a longer validity.
+    pub fn by_difficulty(base: Duration, per_bit: Duration) -> Self {
+        ExpiryPolicy {
+            per_bit,
+            ..ExpiryPolicy::new(base)
+        }
+    }
+
+    /// Expiry for a stamp claiming `bits`, without the grace period. Saturates at
+    /// `Duration::max_value()` instead of overflowing.
+    pub fn expiry_for(&self, bits: u8) -> Duration {
+        self.per_bit
+            .checked_mul(i32::from(bits))
+            .and_then(|per_bits| self.expiry.checked_add(&per_bits))
+            .unwrap_or(Duration::max_value())
+    }
+
+    // `expiry_for` plus the grace period, saturating the same way
+    fn expiry_with_grace(&self, bits: u8) -> Duration {
+        self.expiry_for(bits)
+            .checked_add(&self.grace)
+            .unwrap_or(Duration::max_value())
+    }
+
+    pub fn with_grace(mut self, grace: Duration) -> Self {
+        self.grace = grace;
+        self
+    }
+}
+
+impl Stamp {
+    /// Like `check_bits` with an expiry policy, reporting whether the stamp was only
+    /// accepted thanks to the grace period so callers can log it separately.
+    pub fn check_policy(
+        &self,
+        resource: &str,
+        policy: &ExpiryPolicy,
+        required_bits: u8,
+    ) -> Result<Acceptance, HashcashError> {
+        if !self.resource_matches(resource) {
+            return Err(HashcashError::ResourceMismatch);
+        }
+
+        // the claimed bits pick the expiry, so they have to hold as well as the required ones
+        let expiry = policy.expiry_for(self.requested_bits);
+        let acceptance = if !self.is_expired(&expiry) {
+            Acceptance::Valid
+        } else if !self.is_expired(&policy.expiry_with_grace(self.requested_bits)) {
+            Acceptance::WithinGrace
+        } else {
+            return Err(HashcashError::Expired);
+        };
+
+        self.validate_bits(required_bits.max(self.requested_bits))?;
+
+        Ok(acceptance)
+    }
+
+    /// Like `check_bits` but requires the stamp to be dated on `day` (UTC) instead of an
+    /// expiry window, e.g. for one fresh stamp per day. Stamps from any other day,
+    /// even a minute before midnight, fail with `Expired`.
+    pub fn check_same_day(
+        &self,
+        resource: &str,
+        day: NaiveDate,
+        required_bits: u8,
+    ) -> Result<&Self, HashcashError> {
+        if !self.resource_matches(resource) {
+            return Err(HashcashError::ResourceMismatch);
+        }
+
+        if self.creation_date.date_naive() != day {
+            return Err(HashcashError::Expired);
+        }
+
+        self.validate_bits(required_bits)?;
+
+        Ok(self)
+    }
+
+    /// Like `check_bits` with an arbitrary rule on the creation date instead of an
+    /// expiry, e.g. rejecting weekends. Fails with `Rejected` if `predicate` does.
+    pub fn check_date_predicate(
+        &self,
+        resource: &str,
+        required_bits: u8,
+        predicate: impl Fn(DateTime<Utc>) -> bool,
+    ) -> Result<&Self, HashcashError> {
+        if !self.resource_matches(resource) {
+            return Err(HashcashError::ResourceMismatch);
+        }
+
+        if !predicate(self.creation_date) {
+            return Err(HashcashError::Rejected);
+        }
+
+        self.validate_bits(required_bits)?;
+
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FormatVersion;
+    use chrono::{Datelike, Weekday};
+
+    fn minted_at(creation_date: DateTime<Utc>) -> Stamp {
+        let mut stamp = Stamp::new(FormatVersion::V1, 8, "r".to_string(), None);
+        stamp.creation_date = creation_date;
+        stamp.mint()
+    }
+
+    #[test]
+    fn harder_stamps_live_longer() {
+        let policy = ExpiryPolicy::by_difficulty(Duration::hours(1), Duration::minutes(5));
+        assert_eq!(policy.expiry_for(0), Duration::hours(1));
+        assert_eq!(
+            policy.expiry_for(20),
+            Duration::hours(2) + Duration::minutes(40)
+        );
+        assert_eq!(
+            policy.expiry_for(32),
+            Duration::hours(3) + Duration::minutes(40)
+        );
+
+        let flat = ExpiryPolicy::new(Duration::hours(1));
+        assert_eq!(flat.expiry_for(0), flat.expiry_for(32));
+    }
+
+    #[test]
+    fn grace_extends_the_expiry_for_bits() {
+        // 8 bits live 1h40m, another hour with the grace period
+        let policy = ExpiryPolicy::by_difficulty(Duration::hours(1), Duration::minutes(5))
+            .with_grace(Duration::hours(1));
+        assert_eq!(policy.expiry_for(8), Duration::minutes(100));
+        assert_eq!(policy.expiry_with_grace(8), Duration::minutes(160));
+
+        let accepted = |age| minted_at(Utc::now() - age).check_policy("r", &policy, 8);
+        assert_eq!(accepted(Duration::minutes(90)), Ok(Acceptance::Valid));
+        assert_eq!(
+            accepted(Duration::minutes(110)),
+            Ok(Acceptance::WithinGrace)
+        );
+        assert_eq!(
+            accepted(Duration::minutes(170)),
+            Err(HashcashError::Expired)
+        );
+
+        // nor the server's difficulty
+        assert_eq!(
+            minted_at(Utc::now()).check_policy("r", &policy, 40),
+            Err(HashcashError::InsufficientBits)
+        );
+
+        // the grace period doesn't excuse a missing proof
+        let unminted = Stamp::new(FormatVersion::V1, 32, "r".to_string(), None);
+        assert_eq!(
+            unminted.check_policy("r", &policy, 8),
+            Err(HashcashError::InsufficientBits)
+        );
+    }
+
+    #[test]
+    fn same_day_ends_at_utc_midnight() {
+        let day = NaiveDate::from_ymd_opt(2026, 1, 5).unwrap();
+        let next_day = day.succ_opt().unwrap();
+
+        let last_second = minted_at(day.and_hms_opt(23, 59, 59).unwrap().and_utc());
+        assert!(last_second.check_same_day("r", day, 8).is_ok());
+        assert_eq!(
+            last_second.check_same_day("r", next_day, 8).err(),
+            Some(HashcashError::Expired)
+        );
+
+        let midnight = minted_at(next_day.and_hms_opt(0, 0, 0).unwrap().and_utc());
+        assert!(midnight.check_same_day("r", next_day, 8).is_ok());
+        assert_eq!(
+            midnight.check_same_day("r", day, 8).err(),
+            Some(HashcashError::Expired)
+        );
+    }
+
+    #[test]
+    fn date_predicate_rejects_weekends() {
+        let weekday = |date: DateTime<Utc>| !matches!(date.weekday(), Weekday::Sat | Weekday::Sun);
+        let minted_on = |day| {
+            minted_at(
+                NaiveDate::from_ymd_opt(2026, 1, day)
+                    .unwrap()
+                    .and_hms_opt(12, 0, 0)
+                    .unwrap()
+                    .and_utc(),
+            )
+        };
+
+        // 2026-01-03 and 04 are a Saturday and a Sunday
+        for day in [3, 4] {
+            assert_eq!(
+                minted_on(day).check_date_predicate("r", 8, weekday).err(),
+                Some(HashcashError::Rejected)
+            );
+        }
+        for day in [2, 5] {
+            assert!(minted_on(day).check_date_predicate("r", 8, weekday).is_ok());
+        }
+
+        assert_eq!(
+            minted_on(5).check_date_predicate("s", 8, weekday).err(),
+            Some(HashcashError::ResourceMismatch)
+        );
+    }
+
+    #[test]
+    fn huge_expiries_saturate() {
+        let policy = ExpiryPolicy::by_difficulty(Duration::max_value(), Duration::max_value())
+            .with_grace(Duration::max_value());
+        assert_eq!(policy.expiry_for(255), Duration::max_value());
+        assert_eq!(policy.expiry_with_grace(255), Duration::max_value());
+
+        let stamp = Stamp::new(FormatVersion::V1, 8, "r".to_string(), None).mint();
+        assert_eq!(stamp.check_policy("r", &policy, 8), Ok(Acceptance::Valid));
+    }
+}
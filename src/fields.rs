@@ -0,0 +1,512 @@
+use std::fmt::{self, Display, Formatter};
+use std::sync::OnceLock;
+
+use crate::resource::{percent_decode, percent_encode};
+use crate::{
+    date_layout, extension_algorithm, extension_rounds, is_valid_separator, parse_date,
+    parse_precise_date, Algorithm, DatePrecision, FormatVersion, HashcashError, Stamp, StampFormat,
+    Target, DATE_FORMAT, DAY_DATE_FORMAT, LONG_DATE_FORMAT, MINUTE_DATE_FORMAT,
+    PRECISE_DATE_FORMAT,
+};
+
+// The raw, still encoded fields of a stamp. Splitting a stamp into its fields
+// only happens here so the version-aware field layout lives in one place.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StampFields {
+    pub version: String,
+    pub bits: String,
+    pub date: String,
+    pub resource: String,
+    // only v2 stamps have an explicit algorithm field
+    pub algorithm: Option<String>,
+    // v0 stamps have no extension field at all
+    pub extension: Option<String>,
+    pub salt: String,
+    pub counter: String,
+}
+
+// Longest raw value accepted per field when parsing, so malformed stamps are
+// rejected before anything gets decoded or hashed. The resource is bounded by
+// `StampFormat::max_resource_len`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FieldLimits {
+    // Enough for any u8
+    pub bits: usize,
+    // Widest v0 and v1 date, which may also be 6, 10 or 12 wide. 14 fits a
+    // four-digit year.
+    pub date: usize,
+    // Same for the full precision date of v2 stamps
+    pub precise_date: usize,
+    pub extension: usize,
+    pub salt: usize,
+    // An encoded binary counter, 104 fits a 64 bit counter in base64 and base32
+    pub counter: usize,
+    // Most hash rounds a parsed `rounds=` extension may ask for, every round is
+    // paid again by each check
+    pub rounds: u32,
+    // Highest difficulty accepted when parsing and checking, e.g. to refuse
+    // client-chosen bits no client could mint. The digest length by default.
+    pub max_bits: u8,
+}
+
+impl Default for FieldLimits {
+    fn default() -> Self {
+        FieldLimits {
+            bits: 3,
+            date: 14,
+            precise_date: 26,
+            extension: 512,
+            salt: 128,
+            counter: 104,
+            rounds: 64,
+            max_bits: u8::MAX,
+        }
+    }
+}
+
+impl FieldLimits {
+    /// Accepts `1..=` the digest length of `algorithm` in bits, capped by `max_bits`.
+    pub fn check_bits(&self, bits: u8, algorithm: Algorithm) -> Result<(), HashcashError> {
+        let digest_bits = algorithm.output_len().saturating_mul(8);
+        let max_bits = usize::from(self.max_bits).min(digest_bits);
+        if bits == 0 || usize::from(bits) > max_bits {
+            return Err(HashcashError::InvalidBits);
+        }
+
+        Ok(())
+    }
+
+    /// Checks the length of every field except the resource.
+    pub fn check(&self, fields: &StampFields) -> Result<(), HashcashError> {
+        if fields.bits.is_empty() || fields.bits.len() > self.bits {
+            return Err(HashcashError::InvalidBits);
+        }
+
+        let date_ok = match fields.version.as_str() {
+            "2" => fields.date.len() == self.precise_date,
+            _ => date_layout(&fields.date).is_some() && fields.date.len() <= self.date,
+        };
+        if !date_ok {
+            return Err(HashcashError::InvalidDate);
+        }
+
+        let extension_len = fields.extension.as_ref().map_or(0, String::len);
+        if extension_len > self.extension || fields.salt.len() > self.salt {
+            return Err(HashcashError::FieldTooLong);
+        }
+
+        if fields.counter.len() > self.counter {
+            return Err(HashcashError::InvalidCounter);
+        }
+
+        Ok(())
+    }
+}
+
+impl StampFields {
+    pub fn parse(value: &str) -> Result<Self, HashcashError> {
+        StampFields::parse_with_format(value, &StampFormat::default())
+    }
+
+    /// Splits a stamp on the format's separator.
+    /// The field count has to match the version, so every field sits at a fixed position.
+    pub fn parse_with_format(value: &str, format: &StampFormat) -> Result<Self, HashcashError> {
+        let parts: Vec<&str> = value.split(format.separator).collect();
+        StampFields::from_parts(&parts, format)
+    }
+
+    /// Like `parse_with_format` for raw bytes, e.g. a header value. Only resource
+    /// and extension are validated as UTF-8, the other fields have to be ASCII,
+    /// so garbage is rejected without validating the whole input.
+    pub fn parse_bytes(value: &[u8], format: &StampFormat) -> Result<Self, HashcashError> {
+        let Ok(separator) = u8::try_from(format.separator) else {
+            return match std::str::from_utf8(value) {
+                Ok(value) => StampFields::parse_with_format(value, format),
+                Err(_) => Err(HashcashError::InvalidEncoding),
+            };
+        };
+
+        let parts: Vec<&[u8]> = value.split(|byte| *byte == separator).collect();
+        let extension_index = match parts[0] {
+            b"1" => Some(4),
+            b"2" => Some(5),
+            _ => None,
+        };
+
+        let mut text_parts = Vec::with_capacity(parts.len());
+        for (i, part) in parts.iter().enumerate() {
+            if i != 3 && Some(i) != extension_index && !part.is_ascii() {
+                return Err(HashcashError::InvalidEncoding);
+            }
+
+            match std::str::from_utf8(part) {
+                Ok(v) => text_parts.push(v),
+                Err(_) => return Err(HashcashError::InvalidEncoding),
+            }
+        }
+
+        StampFields::from_parts(&text_parts, format)
+    }
+
+    fn from_parts(parts: &[&str], format: &StampFormat) -> Result<Self, HashcashError> {
+        if !is_valid_separator(format.separator) {
+            return Err(HashcashError::InvalidSeparator);
+        }
+
+        let field_count = match parts[0] {
+            "0" => 6,
+            "1" => 7,
+            "2" => 8,
+            _ => return Err(HashcashError::InvalidVersion),
+        };
+
+        if parts.len() < field_count {
+            return Err(HashcashError::MissingFields);
+        }
+
+        if parts.len() > field_count {
+            return Err(HashcashError::TooManyFields);
+        }
+
+        if parts[3].len() > format.max_resource_len {
+            return Err(HashcashError::ResourceTooLong);
+        }
+
+        let (algorithm, extension, salt_index) = match field_count {
+            6 => (None, None, 4),
+            7 => (None, Some(parts[4].to_string()), 5),
+            _ => (Some(parts[4].to_string()), Some(parts[5].to_string()), 6),
+        };
+
+        let fields = StampFields {
+            version: parts[0].to_string(),
+            bits: parts[1].to_string(),
+            date: parts[2].to_string(),
+            resource: parts[3].to_string(),
+            algorithm,
+            extension,
+            salt: parts[salt_index].to_string(),
+            counter: parts[salt_index + 1].to_string(),
+        };
+        format.limits.check(&fields)?;
+
+        Ok(fields)
+    }
+
+    /// Encodes every field exactly the way it is serialized and hashed.
+    pub fn from_stamp(stamp: &Stamp) -> Self {
+        let extension = match stamp.version {
+            FormatVersion::V0 => None,
+            FormatVersion::V1 | FormatVersion::V2 => {
+                Some(stamp.extension.clone().unwrap_or_default())
+            }
+        };
+
+        let date_format = match (stamp.format.date_precision, stamp.format.four_digit_year) {
+            (DatePrecision::Days, _) => DAY_DATE_FORMAT,
+            (DatePrecision::Minutes, _) => MINUTE_DATE_FORMAT,
+            (DatePrecision::Seconds, true) => LONG_DATE_FORMAT,
+            (DatePrecision::Seconds, false) => DATE_FORMAT,
+        };
+        let (date, algorithm) = match stamp.version {
+            FormatVersion::V2 => (
+                stamp.creation_date.format(PRECISE_DATE_FORMAT).to_string(),
+                Some(stamp.algorithm.to_string()),
+            ),
+            _ => (
+                stamp
+                    .creation_date
+                    .with_timezone(&stamp.format.date_offset)
+                    .format(date_format)
+                    .to_string(),
+                None,
+            ),
+        };
+
+        let resource = if stamp.format.percent_encode_resource {
+            percent_encode(&stamp.resource)
+        } else {
+            stamp.resource.clone()
+        };
+
+        StampFields {
+            version: stamp.version.to_string(),
+            bits: stamp.requested_bits.to_string(),
+            date,
+            resource,
+            algorithm,
+            extension,
+            salt: stamp.format.field_encoding.encode(&stamp.salt),
+            counter: encode_counter(stamp.counter, &stamp.format),
+        }
+    }
+
+    pub fn to_stamp(&self) -> Result<Stamp, HashcashError> {
+        self.to_stamp_with_format(StampFormat::default())
+    }
+
+    pub fn to_stamp_with_format(&self, mut format: StampFormat) -> Result<Stamp, HashcashError> {
+        let version = match self.version.as_str() {
+            "0" => FormatVersion::V0,
+            "1" => FormatVersion::V1,
+            "2" => FormatVersion::V2,
+            _ => return Err(HashcashError::InvalidVersion),
+        };
+
+        let requested_bits = match self.bits.parse::<u8>() {
+            Ok(v) => v,
+            Err(_) => return Err(HashcashError::InvalidBits),
+        };
+
+        let creation_date = match version {
+            FormatVersion::V2 => parse_precise_date(&self.date)?,
+            _ => parse_date(&self.date, &format)?,
+        };
+        // keep writing the date the way it was parsed, otherwise the hash changes
+        if version != FormatVersion::V2 {
+            if let Some((precision, four_digit_year)) = date_layout(&self.date) {
+                format.date_precision = precision;
+                format.four_digit_year = four_digit_year;
+            }
+        }
+
+        let resource = if format.percent_encode_resource {
+            percent_decode(&self.resource)?
+        } else {
+            self.resource.clone()
+        };
+
+        let extension = match version {
+            FormatVersion::V0 => None,
+            FormatVersion::V1 | FormatVersion::V2 => self
+                .extension
+                .clone()
+                .filter(|extension| !extension.is_empty()),
+        };
+
+        let algorithm = match (&self.algorithm, version) {
+            (Some(algorithm), FormatVersion::V2) => Algorithm::try_from(algorithm.as_str())?,
+            (None, FormatVersion::V2) => return Err(HashcashError::MissingFields),
+            _ => extension_algorithm(extension.as_deref())?,
+        };
+        format.limits.check_bits(requested_bits, algorithm)?;
+        let rounds = extension_rounds(extension.as_deref())?;
+        if rounds > format.limits.rounds {
+            return Err(HashcashError::InvalidRounds);
+        }
+
+        let salt = match format.field_encoding.decode(&self.salt) {
+            Some(v) => v,
+            None => return Err(HashcashError::InvalidSalt),
+        };
+
+        let counter = match format
+            .counter_encoding
+            .decode(&self.counter, format.field_encoding)
+        {
+            Some(v) => v,
+            None => return Err(HashcashError::InvalidCounter),
+        };
+        if format.strict_counter && encode_counter(counter, &format) != self.counter {
+            return Err(HashcashError::NonCanonicalCounter);
+        }
+
+        Ok(Stamp {
+            version,
+            requested_bits,
+            creation_date,
+            resource,
+            extension,
+            salt,
+            counter,
+            algorithm,
+            rounds,
+            format,
+            target: Target::default(),
+            counter_step: 1,
+            digest_cache: OnceLock::new(),
+            dirty: false,
+            server_secret: Vec::new(),
+        })
+    }
+
+    pub fn join(&self, separator: char) -> String {
+        let mut fields = vec![
+            self.version.as_str(),
+            &self.bits,
+            &self.date,
+            &self.resource,
+        ];
+        if let Some(algorithm) = &self.algorithm {
+            fields.push(algorithm);
+        }
+        if let Some(extension) = &self.extension {
+            fields.push(extension);
+        }
+        fields.push(&self.salt);
+        fields.push(&self.counter);
+
+        fields.join(&separator.to_string())
+    }
+}
+
+// The counter field in the format's counter encoding. Always the last field, so
+// minting only re-encodes this tail of the hashed payload.
+pub(crate) fn encode_counter(counter: usize, format: &StampFormat) -> String {
+    format
+        .counter_encoding
+        .encode(counter, format.field_encoding)
+}
+
+/// Cheap structural pre-filter for stamps with the default separator, without
+/// allocating or decoding anything. Only checks that the version is known, the
+/// field count matches it and that salt and counter look like standard base64
+/// (alphabet, padding, length a multiple of 4). A well-formed stamp can still
+/// fail to parse, e.g. because of an invalid date.
+pub fn is_well_formed(stamp: &str) -> bool {
+    let field_count = match stamp.split(':').next() {
+        Some("0") => 6,
+        Some("1") => 7,
+        Some("2") => 8,
+        _ => return false,
+    };
+
+    if stamp.split(':').count() != field_count {
+        return false;
+    }
+
+    let mut tail = stamp.rsplit(':');
+    let counter = tail.next().unwrap_or_default();
+    let salt = tail.next().unwrap_or_default();
+
+    is_base64(salt) && is_base64(counter)
+}
+
+fn is_base64(value: &str) -> bool {
+    let data = value.trim_end_matches('=');
+    value.len().is_multiple_of(4)
+        && value.len() - data.len() <= 2
+        && data
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'+' || byte == b'/')
+}
+
+impl Display for StampFields {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(&self.join(':'))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{FieldEncoding, FormatVersion, HashcashError, Stamp, StampFields, StampFormat};
+
+    #[test]
+    fn rejects_rounds_above_the_limit() {
+        let stamp = Stamp::new(FormatVersion::V1, 1, "r".to_string(), None).with_rounds(65);
+        let parsed = Stamp::parse_with_format(&stamp.to_string(), StampFormat::default());
+        assert!(matches!(parsed, Err(HashcashError::InvalidRounds)));
+
+        let stamp = Stamp::new(FormatVersion::V1, 1, "r".to_string(), None).with_rounds(64);
+        assert!(Stamp::parse_with_format(&stamp.to_string(), StampFormat::default()).is_ok());
+
+        let binary = Stamp::new(FormatVersion::V1, 1, "r".to_string(), None).with_rounds(65);
+        let parsed = Stamp::from_bytes(&binary.to_bytes());
+        assert!(matches!(parsed, Err(HashcashError::InvalidRounds)));
+    }
+
+    #[test]
+    fn colons_in_the_extension_dont_shift_salt_and_counter() {
+        for line in [
+            "1:8:260105093015:r:a=b:c:c2FsdA==:MA==",
+            "2:8:20260105T093015.000000000Z:r:sha1:a=b:c:c2FsdA==:MA==",
+        ] {
+            let parsed = StampFields::parse(line);
+            assert_eq!(parsed, Err(HashcashError::TooManyFields));
+        }
+
+        let stamp = Stamp::new(FormatVersion::V1, 1, "r".to_string(), None);
+        let pair = stamp.with_extension_pair("a", "b:c");
+        assert!(matches!(pair, Err(HashcashError::InvalidExtension)));
+
+        // with another separator the colon is just part of the extension
+        let format = StampFormat::with_separator('|').unwrap();
+        let line = "1|8|260105093015|r|a=b:c|c2FsdA==|MA==";
+        let fields = StampFields::parse_with_format(line, &format).unwrap();
+        assert_eq!(fields.extension.as_deref(), Some("a=b:c"));
+        assert_eq!(fields.salt, "c2FsdA==");
+        assert_eq!(fields.counter, "MA==");
+
+        let stamp = Stamp::parse_with_format(line, format).unwrap();
+        assert_eq!(stamp.extension_map().get("a"), Some(&"b:c"));
+        assert_eq!(stamp.to_string(), line);
+    }
+
+    #[test]
+    fn rejects_separators_inside_fields() {
+        for separator in ['/', '+', '=', ';', '.', '%', 'a', 'Z', '7', ' '] {
+            assert_eq!(
+                StampFormat::with_separator(separator),
+                Err(HashcashError::InvalidSeparator)
+            );
+
+            let format = StampFormat {
+                separator,
+                ..StampFormat::default()
+            };
+            let stamp = Stamp::new(FormatVersion::V1, 1, "r".to_string(), None).with_format(format);
+            let parsed = Stamp::parse_with_format(&stamp.to_string(), format);
+            assert!(matches!(parsed, Err(HashcashError::InvalidSeparator)));
+        }
+
+        let format = StampFormat::with_separator('|').unwrap();
+        let stamp = Stamp::new(FormatVersion::V1, 8, "a:b".to_string(), None)
+            .with_format(format)
+            .mint();
+        let parsed = Stamp::parse_with_format(&stamp.to_string(), format).unwrap();
+        assert!(parsed.check_no_expiry("a:b", 8).is_ok());
+    }
+
+    #[test]
+    fn rejects_bits_above_the_format_cap() {
+        let mut format = StampFormat::default();
+        format.limits.max_bits = 8;
+
+        let stamp = Stamp::new(FormatVersion::V1, 9, "r".to_string(), None).mint();
+        let parsed = Stamp::parse_with_format(&stamp.to_string(), format);
+        assert!(matches!(parsed, Err(HashcashError::InvalidBits)));
+        assert!(Stamp::parse_with_format(&stamp.to_string(), StampFormat::default()).is_ok());
+
+        let capped = stamp.with_format(format);
+        assert!(matches!(
+            capped.validate_self(),
+            Err(HashcashError::InvalidBits)
+        ));
+    }
+
+    #[test]
+    fn strict_counters_reject_padded_encodings() {
+        let stamp = Stamp::new(FormatVersion::V1, 1, "r".to_string(), None).with_counter(5);
+        let minimal = stamp.to_string();
+        assert!(minimal.ends_with(":MTAx"));
+
+        // "0101" names the same counter as "101"
+        let prefix = minimal.strip_suffix("MTAx").unwrap();
+        let padded = format!("{}{}", prefix, FieldEncoding::Base64.encode(b"0101"));
+
+        let lenient = StampFormat::default();
+        let strict = StampFormat {
+            strict_counter: true,
+            ..StampFormat::default()
+        };
+        for line in [&minimal, &padded] {
+            let parsed = Stamp::parse_with_format(line, lenient).unwrap();
+            assert_eq!(parsed.counter(), 5);
+        }
+        assert!(Stamp::parse_with_format(&minimal, strict).is_ok());
+        assert!(matches!(
+            Stamp::parse_with_format(&padded, strict),
+            Err(HashcashError::NonCanonicalCounter)
+        ));
+    }
+}
@@ -0,0 +1,36 @@
+use crate::Stamp;
+
+// Counts stamps by their actual leading zero bits, e.g. for a dashboard of
+// verified stamps
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DifficultyHistogram {
+    buckets: [u64; DifficultyHistogram::BUCKETS],
+}
+
+impl Default for DifficultyHistogram {
+    fn default() -> Self {
+        DifficultyHistogram {
+            buckets: [0; DifficultyHistogram::BUCKETS],
+        }
+    }
+}
+
+impl DifficultyHistogram {
+    /// Number of buckets, the last one also counts every stamp with more zero bits.
+    pub const BUCKETS: usize = 64;
+
+    pub fn new() -> Self {
+        DifficultyHistogram::default()
+    }
+
+    /// Counts the stamp, reusing its cached digest if it was already checked.
+    pub fn record(&mut self, stamp: &Stamp) {
+        let bits = stamp.actual_zero_bits() as usize;
+        self.buckets[bits.min(DifficultyHistogram::BUCKETS - 1)] += 1;
+    }
+
+    /// Stamp counts indexed by leading zero bits.
+    pub fn buckets(&self) -> [u64; DifficultyHistogram::BUCKETS] {
+        self.buckets
+    }
+}
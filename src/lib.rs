@@ -0,0 +1,2623 @@
+use std::cmp;
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveDateTime, Timelike, Utc};
+use rand::{Rng, RngCore};
+use sha2::{Digest, Sha256};
+
+mod binary;
+mod cache;
+mod challenge;
+mod difficulty;
+mod encoding;
+mod error;
+mod expiry;
+mod fields;
+mod histogram;
+mod metrics;
+mod miner;
+mod policy;
+mod resource;
+mod reuse;
+mod spend;
+mod stream;
+mod verifier;
+
+pub use cache::VerificationCache;
+pub use challenge::{Challenge, VerifiedStamp};
+pub use difficulty::{expected_iterations, max_bits_for, Difficulty, MintCostSample};
+pub use encoding::{CounterEncoding, FieldEncoding};
+pub use error::HashcashError;
+pub use expiry::{Acceptance, ExpiryPolicy};
+pub use fields::{is_well_formed, FieldLimits, StampFields};
+pub use histogram::DifficultyHistogram;
+pub use metrics::{AtomicMetrics, Metrics};
+pub use miner::{CheckpointingMiner, Miner, WorkRange};
+pub use policy::{Policy, PolicyTable};
+pub use resource::{Resource, ResourceKind};
+pub use reuse::SaltReuseDetector;
+#[cfg(feature = "tokio")]
+pub use spend::spawn_purger;
+#[cfg(feature = "async")]
+pub use spend::AsyncSpendStore;
+pub use spend::{MemorySpendStore, RingSpendStore, ShardedSpendStore, SpendStore, TtlSpendStore};
+pub use verifier::{
+    all_of, any_of, not, AllOf, AnyOf, ClaimedBits, MinBits, Not, ResourceAndExpiry, StampVerifier,
+};
+
+static DATE_FORMAT: &str = "%y%m%d%H%M%S";
+
+// Fallback for emitters that write an unambiguous year
+static LONG_DATE_FORMAT: &str = "%Y%m%d%H%M%S";
+
+// Shorter dates hashcash allows, the python module and the C tool write days by default
+static DAY_DATE_FORMAT: &str = "%y%m%d";
+static MINUTE_DATE_FORMAT: &str = "%y%m%d%H%M";
+
+// Date of v2 stamps, UTC with nanoseconds. This is the basic ISO 8601 form
+// since RFC 3339 would put colons into a colon-separated field.
+static PRECISE_DATE_FORMAT: &str = "%Y%m%dT%H%M%S%.9fZ";
+
+// Difficulty used by the convenience constructors and the CLI
+pub const DEFAULT_BITS: u8 = 20;
+
+// Expiry the example verifies against
+pub const DEFAULT_EXPIRY: Duration = Duration::days(2);
+
+// Smallest salt in bytes accepted by `Stamp::with_salt`
+pub const MIN_SALT_LEN: usize = 8;
+
+// Attempts between clock reads in `Stamp::mint_until`
+const DEADLINE_CHECK_INTERVAL: usize = 4096;
+
+// Characters of the resource shown by the `{:#}` summary
+const RESOURCE_SUMMARY_LEN: usize = 8;
+
+// Extension key carrying the hash algorithm, e.g. `alg=sha256`.
+// Stamps without it are SHA-1 stamps so existing ones stay valid.
+static ALGORITHM_EXTENSION_KEY: &str = "alg";
+
+// Extension key carrying the number of hash rounds, e.g. `rounds=10`
+static ROUNDS_EXTENSION_KEY: &str = "rounds";
+
+// v0 stamps are `0:bits:date:resource:salt:counter`, v1 stamps add an
+// extension field between resource and salt. v2 stamps additionally have an
+// algorithm field before the extension and a full precision date.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FormatVersion {
+    V0 = 0,
+    V1 = 1,
+    V2 = 2,
+}
+
+impl Display for FormatVersion {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            FormatVersion::V0 => write!(f, "0"),
+            FormatVersion::V1 => write!(f, "1"),
+            FormatVersion::V2 => write!(f, "2"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Algorithm {
+    #[default]
+    Sha1,
+    Sha256,
+}
+
+impl Algorithm {
+    pub const ALL: [Algorithm; 2] = [Algorithm::Sha1, Algorithm::Sha256];
+
+    pub fn digest(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Algorithm::Sha1 => sha1_smol::Sha1::from(data).digest().bytes().to_vec(),
+            Algorithm::Sha256 => Sha256::digest(data).to_vec(),
+        }
+    }
+
+    // Like `digest` into a stack buffer wide enough for every algorithm, returns the width
+    fn digest_into(&self, data: &[u8], out: &mut [u8; 32]) -> usize {
+        match self {
+            Algorithm::Sha1 => {
+                out[..20].copy_from_slice(&sha1_smol::Sha1::from(data).digest().bytes());
+                20
+            }
+            Algorithm::Sha256 => {
+                out.copy_from_slice(&Sha256::digest(data));
+                32
+            }
+        }
+    }
+
+    /// Digest width in bytes, the difficulty checks work on any width.
+    pub fn output_len(&self) -> usize {
+        match self {
+            Algorithm::Sha1 => 20,
+            Algorithm::Sha256 => 32,
+        }
+    }
+}
+
+impl Display for Algorithm {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Algorithm::Sha1 => write!(f, "sha1"),
+            Algorithm::Sha256 => write!(f, "sha256"),
+        }
+    }
+}
+
+impl TryFrom<&str> for Algorithm {
+    type Error = HashcashError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "sha1" => Ok(Algorithm::Sha1),
+            "sha256" => Ok(Algorithm::Sha256),
+            _ => Err(HashcashError::InvalidAlgorithm),
+        }
+    }
+}
+
+// Layout options for deployments that deviate from the standard wire format
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StampFormat {
+    // Character between the stamp fields, `:` in standard hashcash
+    pub separator: char,
+
+    // The date field only has a two-digit year, years below the pivot are read
+    // as 20xx and all others as 19xx. Defaults to 70 so `69` is 2069 and `70` is 1970.
+    pub century_pivot: u8,
+
+    // Percent-encodes the resource so stamps can travel inside URLs.
+    // Applies to the hashed form too, so minting and checking must agree on it.
+    pub percent_encode_resource: bool,
+
+    // Longest resource in bytes accepted when parsing. Bounds how much an
+    // untrusted stamp can make every check hash.
+    pub max_resource_len: usize,
+
+    // Writes the v0 and v1 date with a four-digit year, `%Y%m%d%H%M%S`, as some
+    // emitters do. Set on stamps parsed from such a date so they hash the same.
+    // Only applies to dates with seconds.
+    pub four_digit_year: bool,
+
+    // How much of the v0 and v1 date is written, set on parsed stamps from the
+    // width of their date like `four_digit_year`
+    pub date_precision: DatePrecision,
+
+    // Timezone the date field is written in. The canonical form is UTC, other
+    // offsets only exist to verify clients that wrote their local time instead.
+    pub date_offset: FixedOffset,
+
+    // Encoding of the salt and, for binary counters, the counter field
+    pub field_encoding: FieldEncoding,
+
+    pub counter_encoding: CounterEncoding,
+
+    // Whether the extension takes part in the hash. Off hashes the stamp with a
+    // blank extension field while the wire form keeps it, as some implementations
+    // do. Minting and checking must agree on it.
+    pub hash_extension: bool,
+
+    // Rejects counters not in their minimal encoding, e.g. with leading zero
+    // digits, when parsing. They hash like the minimal form, so the same stamp
+    // would otherwise have several wire forms.
+    pub strict_counter: bool,
+
+    // Length limits for the remaining fields
+    pub limits: FieldLimits,
+}
+
+impl Default for StampFormat {
+    fn default() -> Self {
+        StampFormat {
+            separator: ':',
+            century_pivot: 70,
+            percent_encode_resource: false,
+            max_resource_len: 512,
+            four_digit_year: false,
+            date_precision: DatePrecision::default(),
+            date_offset: FixedOffset::east_opt(0).unwrap(),
+            field_encoding: FieldEncoding::default(),
+            counter_encoding: CounterEncoding::default(),
+            hash_extension: true,
+            strict_counter: false,
+            limits: FieldLimits::default(),
+        }
+    }
+}
+
+impl StampFormat {
+    /// The layout of the python hashcash module and the C `hashcash` tool: salt and
+    /// counter are kept as written (`FieldEncoding::Text`,
+    /// `CounterEncoding::Base64Digits`) and new stamps are dated by day.
+    pub fn reference() -> Self {
+        StampFormat {
+            date_precision: DatePrecision::Days,
+            field_encoding: FieldEncoding::Text,
+            counter_encoding: CounterEncoding::Base64Digits,
+            ..StampFormat::default()
+        }
+    }
+
+    /// The default format with another separator, rejecting characters that can
+    /// occur inside the fields: alphanumerics, whitespace and `+/=;.%`.
+    pub fn with_separator(separator: char) -> Result<Self, HashcashError> {
+        if !is_valid_separator(separator) {
+            return Err(HashcashError::InvalidSeparator);
+        }
+
+        Ok(StampFormat {
+            separator,
+            ..StampFormat::default()
+        })
+    }
+}
+
+// Whether a separator can't be confused with the content of a field: base64 and
+// base32 salts and counters, dates, `key=value;` extensions and percent-encoding
+fn is_valid_separator(separator: char) -> bool {
+    !separator.is_alphanumeric()
+        && !separator.is_whitespace()
+        && !separator.is_control()
+        && !"+/=;.%".contains(separator)
+}
+
+// Resolution of the v0 and v1 date field
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DatePrecision {
+    // `%y%m%d`
+    Days,
+    // `%y%m%d%H%M`
+    Minutes,
+    // `%y%m%d%H%M%S`
+    #[default]
+    Seconds,
+}
+
+// Difficulty mode used by `mint` and `check`, both sides have to agree on it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Target {
+    // The digest must start with `requested_bits` zero bits
+    #[default]
+    BitPrefix,
+
+    // The digest must end with `requested_bits` zero bits, as used by some
+    // hashcash-like schemes
+    BitSuffix,
+
+    // The hex digest must start with `requested_bits / 4` (rounded up) zeros.
+    // Same as `BitPrefix` for multiples of 4, other bit counts round up to the
+    // next hex digit.
+    NibblePrefix,
+
+    // The digest must start with `requested_bits / 8` (rounded up) zero bytes, for
+    // interop with tools that count bytes. A coarser unit: 16 bits means 2 zero
+    // bytes, 17 bits already needs 3.
+    BytePrefix,
+
+    // The first 160 bits of the digest, read as a big-endian integer, must be
+    // below the threshold. Allows tuning difficulty between powers of two.
+    Threshold([u8; 20]),
+}
+
+impl Target {
+    /// Parses a threshold from up to 40 hex digits, shorter inputs are left-padded with zeros.
+    pub fn from_hex(hex: &str) -> Result<Self, HashcashError> {
+        if hex.is_empty() || hex.len() > 40 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(HashcashError::InvalidTarget);
+        }
+
+        let padded = format!("{:0>40}", hex);
+        let mut threshold = [0u8; 20];
+        for (i, byte) in threshold.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&padded[i * 2..i * 2 + 2], 16).unwrap();
+        }
+
+        Ok(Target::Threshold(threshold))
+    }
+
+    // Whether `digest` meets `bits` in this mode, a threshold ignores the bits
+    fn met(&self, digest: &[u8], bits: u8) -> bool {
+        match self {
+            Target::BitPrefix => count_leading_zero_bits(digest) >= u32::from(bits),
+            Target::BitSuffix => trailing_zero_bits(digest) >= u32::from(bits),
+            Target::NibblePrefix => {
+                let nibbles = u32::from(bits).div_ceil(4);
+                count_leading_zero_bits(digest) / 4 >= nibbles
+            }
+            Target::BytePrefix => {
+                let bytes = usize::from(bits).div_ceil(8);
+                digest.iter().take_while(|byte| **byte == 0).count() >= bytes
+            }
+            Target::Threshold(threshold) => {
+                let len = digest.len().min(threshold.len());
+                digest[..len] < threshold[..len]
+            }
+        }
+    }
+}
+
+// What `Stamp::normalize` did
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Normalization {
+    // The serialized and hashed form changed
+    pub changed: bool,
+    // The stamp no longer meets its difficulty and has to be minted again
+    pub remint_required: bool,
+}
+
+// Why `Stamp::mint_with` stopped, each variant holds the stamp in its final state
+pub enum MintOutcome {
+    Solved(Stamp),
+    Cancelled(Stamp),
+    Exhausted(Stamp),
+}
+
+/// Parses and checks a stamp in one call, the common case for a received `X-Hashcash` header.
+pub fn verify(
+    stamp: &str,
+    resource: &str,
+    expiry_duration: Duration,
+    required_bits: u8,
+) -> Result<(), HashcashError> {
+    Stamp::try_from(stamp.to_string())?.check_bits(resource, &expiry_duration, required_bits)?;
+    Ok(())
+}
+
+/// Verifies the stamps of repeated `X-Hashcash` headers, with or without the
+/// header name. Accepts if any stamp is valid, or only if all are with `require_all`.
+/// Fails with the first error otherwise, `MissingFields` for an empty list.
+pub fn verify_header_list(
+    lines: &[&str],
+    resource: &str,
+    expiry_duration: Duration,
+    required_bits: u8,
+    require_all: bool,
+) -> Result<(), HashcashError> {
+    let results = lines.iter().map(|line| {
+        let stamp = match line.split_once(':') {
+            Some((name, value)) if name.trim().eq_ignore_ascii_case("x-hashcash") => value,
+            _ => line,
+        };
+        verify(stamp.trim(), resource, expiry_duration, required_bits)
+    });
+
+    if require_all {
+        if lines.is_empty() {
+            return Err(HashcashError::MissingFields);
+        }
+        return results.collect();
+    }
+
+    let mut first_error = None;
+    for result in results {
+        match result {
+            Ok(()) => return Ok(()),
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    Err(first_error.unwrap_or(HashcashError::MissingFields))
+}
+
+// Internal counterpart of `MintOutcome` for the in-place minting loop
+enum SearchStop {
+    Solved,
+    Cancelled,
+    Exhausted,
+}
+
+#[derive(Clone)]
+pub struct Stamp {
+    // Hashcash format version
+    version: FormatVersion,
+
+    // Number of "partial pre-image" (zero) bits in the hashed code.
+    // refered to as 'w' in the original paper
+    requested_bits: u8,
+
+    // The time that the stamp was created/sent, in the format YYMMDD[hhmm[ss]].
+    creation_date: DateTime<Utc>,
+
+    // Resource which is being transmitted, e.g., an IP address or email address.
+    resource: String,
+
+    // Extension (optional; ignored in version 1).
+    // An empty extension field and no extension serialize the same, so an empty
+    // extension is always stored as None to keep round-trips idempotent.
+    extension: Option<String>,
+
+    // String of random alphanumeric characters
+    // c implementation defaults to a length of 16 so do we
+    salt: Vec<u8>,
+
+    // Used to add variance after each hashing until we bruteforce a valid one
+    counter: usize,
+
+    // Hash algorithm used for minting and checking, carried in the extension
+    algorithm: Algorithm,
+
+    // Times the digest is hashed, carried in the extension
+    rounds: u32,
+
+    // Wire format used for serializing and hashing, not part of the stamp itself
+    format: StampFormat,
+
+    // How the digest is compared against the difficulty, not part of the wire format
+    target: Target,
+
+    // Amount added to the counter after each failed attempt while minting
+    counter_step: usize,
+
+    // Digest of the current serialized stamp, cleared whenever a field changes
+    digest_cache: OnceLock<Vec<u8>>,
+
+    // Set when a hashed field changed after minting or parsing, the counter no
+    // longer belongs to the fields until the stamp is minted again
+    dirty: bool,
+
+    // Prepended to the hashed payload, not part of the wire format
+    server_secret: Vec<u8>,
+}
+
+impl Stamp {
+    #[must_use = "creating a stamp does nothing until it is minted"]
+    pub fn new(
+        version: FormatVersion,
+        requested_bits: u8,
+        resource: String,
+        extension: Option<String>,
+    ) -> Self {
+        let salt = random_salt();
+
+        let creation_date = chrono::offset::Utc::now();
+
+        // v0 stamps have no extension field to carry one
+        let extension =
+            extension.filter(|extension| !extension.is_empty() && version != FormatVersion::V0);
+
+        // invalid settings fall back to the defaults, use `with_algorithm` and
+        // `with_rounds` to set them explicitly
+        let algorithm = extension_algorithm(extension.as_deref()).unwrap_or_default();
+        let rounds = extension_rounds(extension.as_deref()).unwrap_or(1);
+
+        Stamp {
+            version,
+            requested_bits,
+            creation_date,
+            resource,
+            extension,
+            salt,
+            counter: 0,
+            algorithm,
+            rounds,
+            format: StampFormat::default(),
+            target: Target::default(),
+            counter_step: 1,
+            digest_cache: OnceLock::new(),
+            dirty: false,
+            server_secret: Vec::new(),
+        }
+    }
+
+    /// Like `new` but rejects a difficulty no digest of the stamp's algorithm can
+    /// meet, see `feasible_bits`. The algorithm is the one an `alg=` extension
+    /// selects, SHA-1 by default.
+    pub fn try_new(
+        version: FormatVersion,
+        requested_bits: u8,
+        resource: String,
+        extension: Option<String>,
+    ) -> Result<Self, HashcashError> {
+        let stamp = Stamp::new(version, requested_bits, resource, extension);
+        Stamp::feasible_bits(requested_bits, stamp.algorithm)?;
+        Ok(stamp)
+    }
+
+    /// Assembles a stamp from its decoded fields without minting or parsing,
+    /// e.g. to test `check` against a known counter and date.
+    #[cfg(feature = "testing")]
+    pub fn from_parts(
+        version: FormatVersion,
+        requested_bits: u8,
+        creation_date: DateTime<Utc>,
+        resource: String,
+        extension: Option<String>,
+        salt: Vec<u8>,
+        counter: usize,
+    ) -> Self {
+        let extension = extension.filter(|extension| !extension.is_empty());
+        let algorithm = extension_algorithm(extension.as_deref()).unwrap_or_default();
+        let rounds = extension_rounds(extension.as_deref()).unwrap_or(1);
+
+        Stamp {
+            version,
+            requested_bits,
+            creation_date,
+            resource,
+            extension,
+            salt,
+            counter,
+            algorithm,
+            rounds,
+            format: StampFormat::default(),
+            target: Target::default(),
+            counter_step: 1,
+            digest_cache: OnceLock::new(),
+            dirty: false,
+            server_secret: Vec::new(),
+        }
+    }
+
+    /// Parses a stamp serialized with the given format, e.g. a non-standard separator.
+    pub fn parse_with_format(value: &str, format: StampFormat) -> Result<Self, HashcashError> {
+        StampFields::parse_with_format(value, &format)?.to_stamp_with_format(format)
+    }
+
+    /// Parses a line printed by the C `hashcash -m` tool. Surrounding whitespace and
+    /// its `hashcash stamp:` or `X-Hashcash:` prefix are ignored, the stamp itself is
+    /// read with `StampFormat::reference()` so salt and counter hash as written.
+    pub fn parse_reference_output(value: &str) -> Result<Self, HashcashError> {
+        let mut value = value.trim();
+        for prefix in ["hashcash stamp:", "X-Hashcash:"] {
+            if value.len() >= prefix.len()
+                && value.is_char_boundary(prefix.len())
+                && value[..prefix.len()].eq_ignore_ascii_case(prefix)
+            {
+                value = value[prefix.len()..].trim_start();
+            }
+        }
+        Stamp::parse_with_format(value, StampFormat::reference())
+    }
+
+    /// Like `parse_with_format`, also reporting whether `value` already is the
+    /// canonical form. If not, the proof only holds for the original input since
+    /// re-serializing changes the hashed bytes.
+    pub fn parse_reporting_canonical(
+        value: &str,
+        format: StampFormat,
+    ) -> Result<(Self, bool), HashcashError> {
+        let stamp = Stamp::parse_with_format(value, format)?;
+        let was_canonical = stamp.canonical_string() == value;
+        Ok((stamp, was_canonical))
+    }
+
+    /// Serializes (and therefore hashes) the stamp using the given format.
+    pub fn with_format(mut self, format: StampFormat) -> Self {
+        let before = self.proof_inputs();
+        self.format = format;
+        self.invalidate_digest_since(before);
+        self
+    }
+
+    /// Prepends a secret to everything hashed when minting and checking, so tables
+    /// precomputed without it are useless. Both sides need the same secret and
+    /// nobody without it can verify the stamps, which is sometimes the point.
+    pub fn with_server_secret(mut self, secret: &[u8]) -> Self {
+        self.server_secret = secret.to_vec();
+        // the fields stay the same, so a parsed stamp stays checkable
+        self.digest_cache = OnceLock::new();
+        self
+    }
+
+    /// Selects the difficulty mode, the checking side must use the same target.
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Binds the stamp to another resource, e.g. a generic stamp minted ahead of
+    /// time. The resource is hashed, so this invalidates the proof: the counter is
+    /// reset and the stamp has to be minted again.
+    pub fn with_resource(mut self, resource: String) -> Self {
+        let before = self.proof_inputs();
+        self.resource = resource;
+        self.counter = 0;
+        self.invalidate_digest_since(before);
+        self
+    }
+
+    /// Sets the counter minting starts from, e.g. to resume an earlier mint.
+    pub fn with_counter(mut self, counter: usize) -> Self {
+        let before = self.proof_inputs();
+        self.counter = counter;
+        self.invalidate_digest_since(before);
+        self
+    }
+
+    /// Starts minting from a random counter instead of 0.
+    ///
+    /// Together with `with_counter_step` this only hides minting patterns from
+    /// traffic analysis, it is cosmetic and not a security property.
+    pub fn with_random_counter(self) -> Self {
+        let counter = with_rng(|rng| rng.gen::<u32>()) as usize;
+        self.with_counter(counter)
+    }
+
+    /// Sets how far the counter advances per attempt, the search stays deterministic.
+    /// Odd steps visit every counter before wrapping around.
+    pub fn with_counter_step(mut self, step: usize) -> Self {
+        self.counter_step = step.max(1);
+        self
+    }
+
+    /// Replaces the random salt, rejecting salts shorter than `MIN_SALT_LEN` bytes.
+    pub fn with_salt(self, salt: Vec<u8>) -> Result<Self, HashcashError> {
+        self.with_salt_min_len(salt, MIN_SALT_LEN)
+    }
+
+    /// Like `with_salt` with a custom minimum salt length in bytes.
+    ///
+    /// The salt is what stops an attacker from precomputing stamps for a resource
+    /// ahead of time, a short salt makes such tables small enough to be practical.
+    pub fn with_salt_min_len(
+        mut self,
+        salt: Vec<u8>,
+        min_len: usize,
+    ) -> Result<Self, HashcashError> {
+        if salt.len() < min_len {
+            return Err(HashcashError::InsufficientSalt);
+        }
+
+        let before = self.proof_inputs();
+        self.salt = salt;
+        self.invalidate_digest_since(before);
+        Ok(self)
+    }
+
+    /// Replaces the random salt with the first 16 bytes of HMAC-SHA256(secret, resource).
+    ///
+    /// A server can re-derive the salt of any stamp it issued for a resource instead
+    /// of storing it, while clients without the secret can't predict it. Every stamp
+    /// for the same resource shares the salt, so only the date tells them apart and
+    /// leaking the secret makes all salts predictable.
+    pub fn with_derived_salt(mut self, secret: &[u8]) -> Self {
+        let mut salt = hmac_sha256(secret, self.resource.as_bytes());
+        salt.truncate(16);
+        let before = self.proof_inputs();
+        self.salt = salt;
+        self.invalidate_digest_since(before);
+        self
+    }
+
+    /// Trims whitespace around the resource and the extension pairs and drops
+    /// empty pairs. Any change alters the hashed bytes and thereby breaks the proof.
+    pub fn normalize(&mut self) -> Normalization {
+        let before = self.canonical_bytes();
+        let proof_before = self.proof_inputs();
+
+        let resource = self.resource.trim().to_string();
+        let extension = self.extension.take().map(|extension| {
+            extension
+                .split(';')
+                .map(|pair| match pair.split_once('=') {
+                    Some((key, value)) => format!("{}={}", key.trim(), value.trim()),
+                    None => pair.trim().to_string(),
+                })
+                .filter(|pair| !pair.is_empty())
+                .collect::<Vec<_>>()
+                .join(";")
+        });
+        self.resource = resource;
+        self.extension = extension.filter(|extension| !extension.is_empty());
+
+        // trimmed keys may only now name the algorithm or rounds
+        if self.version != FormatVersion::V2 {
+            self.algorithm =
+                extension_algorithm(self.extension.as_deref()).unwrap_or(self.algorithm);
+        }
+        self.rounds = extension_rounds(self.extension.as_deref()).unwrap_or(self.rounds);
+
+        let changed = self.canonical_bytes() != before;
+        self.invalidate_digest_since(proof_before);
+
+        Normalization {
+            changed,
+            remint_required: self.validate_self().is_err(),
+        }
+    }
+
+    /// Draws a fresh salt and resets the counter, e.g. to re-issue a challenge for
+    /// the same resource. The stamp has to be minted again afterwards.
+    pub fn reroll_salt(&mut self) {
+        let before = self.proof_inputs();
+        self.salt = random_salt();
+        self.counter = 0;
+        self.invalidate_digest_since(before);
+    }
+
+    /// Mints a V1 stamp with `DEFAULT_BITS` for the given email address.
+    pub fn for_email(email: &str) -> Self {
+        Stamp::new(FormatVersion::V1, DEFAULT_BITS, email.to_string(), None).mint()
+    }
+
+    /// Sets the hash algorithm and records it as `alg=<name>` in the extension,
+    /// v2 stamps carry it in their own field instead.
+    ///
+    /// v0 stamps have no extension to record it in and stay SHA-1, any other
+    /// algorithm leaves them unchanged.
+    pub fn with_algorithm(mut self, algorithm: Algorithm) -> Self {
+        if self.version == FormatVersion::V0 && algorithm != Algorithm::Sha1 {
+            return self;
+        }
+
+        let before = self.proof_inputs();
+        let value = (algorithm != Algorithm::Sha1 && self.version != FormatVersion::V2)
+            .then(|| algorithm.to_string());
+        self.set_extension_value(ALGORITHM_EXTENSION_KEY, value.as_deref());
+        self.algorithm = algorithm;
+        self.invalidate_digest_since(before);
+        self
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    /// Hashes the digest `rounds` times in total and records it as `rounds=<n>`
+    /// in the extension.
+    ///
+    /// Every round makes each minting attempt more expensive, also on GPUs, at the
+    /// price of slowing down honest clients and every `check` by the same factor.
+    ///
+    /// v0 stamps have no extension and are always hashed once, for them this does
+    /// nothing.
+    pub fn with_rounds(mut self, rounds: u32) -> Self {
+        if self.version == FormatVersion::V0 {
+            return self;
+        }
+
+        let before = self.proof_inputs();
+        let rounds = rounds.max(1);
+        let value = (rounds != 1).then(|| rounds.to_string());
+        self.set_extension_value(ROUNDS_EXTENSION_KEY, value.as_deref());
+        self.rounds = rounds;
+        self.invalidate_digest_since(before);
+        self
+    }
+
+    pub fn rounds(&self) -> u32 {
+        self.rounds
+    }
+
+    /// Sets `key=value` in the extension, pairs are kept sorted by key.
+    ///
+    /// Rejects v0 stamps, which have no extension, as well as keys or values that
+    /// would break the `key=value;...` grammar or the stamp's fields. The `alg` and
+    /// `rounds` keys are reserved for `with_algorithm` and `with_rounds`.
+    pub fn with_extension_pair(mut self, key: &str, value: &str) -> Result<Self, HashcashError> {
+        let reserved = [ALGORITHM_EXTENSION_KEY, ROUNDS_EXTENSION_KEY];
+        let is_invalid = |part: &str| part.contains([';', '=', ':', self.format.separator]);
+
+        if self.version == FormatVersion::V0
+            || key.is_empty()
+            || reserved.contains(&key)
+            || is_invalid(key)
+            || is_invalid(value)
+        {
+            return Err(HashcashError::InvalidExtension);
+        }
+
+        let before = self.proof_inputs();
+        self.set_extension_value(key, Some(value));
+        self.invalidate_digest_since(before);
+        Ok(self)
+    }
+
+    /// The extension's `key=value` pairs, independent of their order. Parsed stamps
+    /// still serialize and hash their extension exactly as received.
+    pub fn extension_map(&self) -> BTreeMap<&str, &str> {
+        self.extension
+            .as_deref()
+            .unwrap_or_default()
+            .split(';')
+            .filter_map(|pair| pair.split_once('='))
+            .collect()
+    }
+
+    fn set_extension_value(&mut self, key: &str, value: Option<&str>) {
+        let extension = self.extension.take().unwrap_or_default();
+        let extension = set_extension_value(&extension, key, value);
+
+        self.extension = (!extension.is_empty()).then_some(extension);
+    }
+
+    pub fn requested_bits(&self) -> u8 {
+        self.requested_bits
+    }
+
+    /// The difficulty the stamp claims. It is chosen by the client and therefore
+    /// untrusted, only useful for logging. Use `check_bits` to enforce a difficulty.
+    pub fn bits(&self) -> u8 {
+        self.requested_bits
+    }
+
+    pub fn counter(&self) -> usize {
+        self.counter
+    }
+
+    /// The date field exactly as it appears in the serialized stamp, `%y%m%d%H%M%S`
+    /// for v0 and v1 stamps.
+    pub fn date_field(&self) -> String {
+        StampFields::from_stamp(self).date
+    }
+
+    /// The salt field exactly as it appears in the serialized stamp.
+    pub fn salt_base64(&self) -> String {
+        StampFields::from_stamp(self).salt
+    }
+
+    /// The counter field exactly as it appears in the serialized stamp.
+    pub fn counter_base64(&self) -> String {
+        StampFields::from_stamp(self).counter
+    }
+
+    /// The counter field decoded from its field encoding, i.e. the bytes that get
+    /// encoded on the wire. The counter's binary digits as ASCII for binary
+    /// counters, the field itself for base-64 numerals.
+    pub fn counter_bytes(&self) -> Vec<u8> {
+        match self.format.counter_encoding {
+            CounterEncoding::Binary => format!("{:b}", self.counter).into_bytes(),
+            CounterEncoding::Base64Digits => {
+                fields::encode_counter(self.counter, &self.format).into_bytes()
+            }
+        }
+    }
+
+    /// Number of leading zero bits the stamp's digest actually has,
+    /// regardless of its claimed `requested_bits`.
+    pub fn actual_zero_bits(&self) -> u32 {
+        count_leading_zero_bits(self.digest())
+    }
+
+    /// Estimated attempts behind the stamp, `2^actual_zero_bits` saturating at `u64::MAX`.
+    pub fn work_done(&self) -> u64 {
+        1u64.checked_shl(self.actual_zero_bits())
+            .unwrap_or(u64::MAX)
+    }
+
+    /// Orders stamps by `work_done`, e.g. `stamps.sort_by(Stamp::cmp_work)`.
+    pub fn cmp_work(&self, other: &Stamp) -> cmp::Ordering {
+        self.work_done().cmp(&other.work_done())
+    }
+
+    /// Whether the stamp's digest has at least `bits` leading zero bits,
+    /// independent of the difficulty it claims.
+    pub fn difficulty_met(&self, bits: u8) -> bool {
+        self.actual_zero_bits() >= u32::from(bits)
+    }
+
+    /// Bits missing to reach `required`, negative if the stamp has bits to spare.
+    /// E.g. for telling a rejected client how much more work is needed.
+    pub fn difficulty_deficit(&self, required: u8) -> i32 {
+        i32::from(required) - self.actual_zero_bits() as i32
+    }
+
+    /// Zero bits achieved beyond the claimed `requested_bits`, 0 for stamps that
+    /// fall short. A rough signal for clients that mint harder than they need to.
+    pub fn bits_surplus(&self) -> u32 {
+        self.actual_zero_bits()
+            .saturating_sub(u32::from(self.requested_bits))
+    }
+
+    /// Whether the stamp would pass at each difficulty from 1 to 32 bits,
+    /// index `i` holds the result for `i + 1` bits.
+    pub fn zero_bit_report(&self) -> Vec<bool> {
+        (1..=32).map(|bits| self.difficulty_met(bits)).collect()
+    }
+
+    pub fn get_requested_zeros(&self) -> String {
+        str::repeat("0", self.requested_bits as usize)
+    }
+
+    /// Hex SHA-1 digest for display and debugging, checks never format the digest.
+    pub fn to_sha1_hash(&self) -> String {
+        to_hex(&self.sha1_digest())
+    }
+
+    /// Like `to_sha1_hash` as a string of `0` and `1`.
+    pub fn to_binary_sha1_hash(&self) -> String {
+        to_binary(&self.sha1_digest())
+    }
+
+    /// The first `n` bits of `to_binary_sha1_hash`, `None` beyond the 160 bits of SHA-1.
+    pub fn leading_bits(&self, n: usize) -> Option<String> {
+        let mut bits = self.to_binary_sha1_hash();
+        if n > bits.len() {
+            return None;
+        }
+
+        bits.truncate(n);
+        Some(bits)
+    }
+
+    /// The bytes that are hashed when minting and checking.
+    ///
+    /// This is the stability boundary of the proof: changing its output invalidates
+    /// every existing stamp, while `Display` is free to change.
+    pub fn canonical_bytes(&self) -> Vec<u8> {
+        self.canonical_string().into_bytes()
+    }
+
+    /// Whether the stamp serializes back to `original`, ignoring surrounding
+    /// whitespace such as a trailing newline. Every field has to match exactly.
+    pub fn canonical_eq(&self, original: &str) -> bool {
+        self.canonical_bytes() == original.trim().as_bytes()
+    }
+
+    /// Whether both stamps are the same challenge, equal in every field but the
+    /// counter, e.g. a client that minted the same challenge again.
+    pub fn same_challenge(&self, other: &Stamp) -> bool {
+        let mut fields = StampFields::from_stamp(self);
+        let mut other_fields = StampFields::from_stamp(other);
+        fields.counter.clear();
+        other_fields.counter.clear();
+        fields == other_fields
+    }
+
+    /// Exactly the bytes fed to the hasher, for comparing with a foreign verifier.
+    /// The server secret followed by `canonical_bytes`, with a blank extension
+    /// unless `StampFormat::hash_extension`. Further rounds only hash the previous digest.
+    pub fn hashed_payload(&self) -> Vec<u8> {
+        let mut payload = self.server_secret.clone();
+        payload.extend(self.hashed_fields().into_bytes());
+        payload
+    }
+
+    // The wire form as the proof covers it, with a blank extension unless it's hashed
+    fn hashed_fields(&self) -> String {
+        if self.format.hash_extension {
+            return self.canonical_string();
+        }
+
+        let mut fields = StampFields::from_stamp(self);
+        if let Some(extension) = &mut fields.extension {
+            extension.clear();
+        }
+        fields.join(self.format.separator)
+    }
+
+    // `hashed_payload` written into one buffer without the intermediate `StampFields`
+    // and their strings, for `check_fast`. Has to produce the same bytes.
+    fn write_hashed_payload(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.server_secret);
+        self.write_fields(out, self.format.hash_extension).unwrap();
+    }
+
+    // The wire form field by field, with a blank extension unless `with_extension`
+    fn write_fields(&self, out: &mut impl io::Write, with_extension: bool) -> io::Result<()> {
+        let mut separator = [0u8; 4];
+        let separator = self.format.separator.encode_utf8(&mut separator).as_bytes();
+
+        write!(out, "{}", self.version)?;
+        out.write_all(separator)?;
+        write!(out, "{}", self.requested_bits)?;
+        out.write_all(separator)?;
+
+        match self.version {
+            FormatVersion::V2 => {
+                let date = self.creation_date;
+                write_year(out, date.year())?;
+                write!(
+                    out,
+                    "{:02}{:02}T{:02}{:02}{:02}.{:09}Z",
+                    date.month(),
+                    date.day(),
+                    date.hour(),
+                    date.minute(),
+                    date.second() + date.nanosecond() / 1_000_000_000,
+                    date.nanosecond() % 1_000_000_000
+                )?;
+            }
+            _ => {
+                let date = self.creation_date.with_timezone(&self.format.date_offset);
+                match (self.format.date_precision, self.format.four_digit_year) {
+                    (DatePrecision::Seconds, true) => write_year(out, date.year())?,
+                    _ => write!(out, "{:02}", date.year().rem_euclid(100))?,
+                }
+                write!(out, "{:02}{:02}", date.month(), date.day())?;
+                if self.format.date_precision != DatePrecision::Days {
+                    write!(out, "{:02}{:02}", date.hour(), date.minute())?;
+                }
+                if self.format.date_precision == DatePrecision::Seconds {
+                    let second = date.second() + date.nanosecond() / 1_000_000_000;
+                    write!(out, "{:02}", second)?;
+                }
+            }
+        }
+        out.write_all(separator)?;
+
+        if self.format.percent_encode_resource {
+            resource::percent_encode_into(&self.resource, out)?;
+        } else {
+            out.write_all(self.resource.as_bytes())?;
+        }
+        out.write_all(separator)?;
+
+        if self.version == FormatVersion::V2 {
+            write!(out, "{}", self.algorithm)?;
+            out.write_all(separator)?;
+        }
+        if self.version != FormatVersion::V0 {
+            if with_extension {
+                out.write_all(self.extension.as_deref().unwrap_or_default().as_bytes())?;
+            }
+            out.write_all(separator)?;
+        }
+
+        self.format.field_encoding.encode_into(&self.salt, out)?;
+        out.write_all(separator)?;
+        self.format
+            .counter_encoding
+            .encode_into(self.counter, self.format.field_encoding, out)
+    }
+
+    // Upper bound of what `write_hashed_payload` writes, so it never reallocates
+    fn hashed_payload_capacity(&self) -> usize {
+        let resource_len = match self.format.percent_encode_resource {
+            true => 3 * self.resource.len(),
+            false => self.resource.len(),
+        };
+        let extension_len = self.extension.as_ref().map_or(0, String::len);
+
+        // salt and counter encodings grow by at most 8/5, the rest are short
+        // fixed-width fields and up to 7 separators
+        self.server_secret.len()
+            + resource_len
+            + extension_len
+            + 2 * self.salt.len()
+            + 2 * usize::BITS as usize
+            + 64
+            + 7 * self.format.separator.len_utf8()
+    }
+
+    fn canonical_string(&self) -> String {
+        // v0 stamps use the short form without an extension field
+        StampFields::from_stamp(self).join(self.format.separator)
+    }
+
+    // SHA-1 stamps share the cached digest, other algorithms hash again
+    fn sha1_digest(&self) -> Vec<u8> {
+        match self.algorithm {
+            Algorithm::Sha1 => self.digest().to_vec(),
+            _ => self.hash(Algorithm::Sha1, &self.hashed_payload()),
+        }
+    }
+
+    // Digest under the stamp's own algorithm, computed once per unchanged stamp
+    fn digest(&self) -> &[u8] {
+        self.digest_cache.get_or_init(|| self.compute_digest())
+    }
+
+    fn compute_digest(&self) -> Vec<u8> {
+        self.hash(self.algorithm, &self.hashed_payload())
+    }
+
+    // Applies the stamp's hash rounds
+    fn hash(&self, algorithm: Algorithm, payload: &[u8]) -> Vec<u8> {
+        let mut digest = algorithm.digest(payload);
+        for _ in 1..self.rounds {
+            digest = algorithm.digest(&digest);
+        }
+        digest
+    }
+
+    // Must be called by anything that changes a serialized field
+    fn invalidate_digest(&mut self) {
+        self.digest_cache = OnceLock::new();
+        self.dirty = true;
+    }
+
+    // Everything the proof depends on
+    fn proof_inputs(&self) -> (Vec<u8>, Algorithm, u32) {
+        (self.hashed_payload(), self.algorithm, self.rounds)
+    }
+
+    // Like `invalidate_digest` with the `proof_inputs` from before a change, only
+    // a change of them invalidates, e.g. setting the format the stamp already has
+    // keeps its proof
+    fn invalidate_digest_since(&mut self, before: (Vec<u8>, Algorithm, u32)) {
+        if self.proof_inputs() != before {
+            self.invalidate_digest();
+        }
+    }
+
+    /// Whether a hashed field changed since the stamp was minted or parsed.
+    /// Checking a dirty stamp fails with `StaleProof` until it is minted again.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Searches for a counter meeting the target.
+    ///
+    /// # Panics
+    ///
+    /// If the difficulty exceeds the digest's width, which no counter can meet.
+    /// `try_new` and `feasible_bits` reject such bits up front.
+    #[must_use = "mint consumes the stamp, dropping the result discards the work"]
+    pub fn mint(mut self) -> Self {
+        self.mint_ref();
+        self
+    }
+
+    /// Mints in place, e.g. for a stamp held in a struct field. Panics like `mint`.
+    pub fn mint_ref(&mut self) {
+        self.assert_feasible(8 * self.algorithm.output_len());
+        self.search(None, None);
+    }
+
+    // No digest has more zero bits than it is wide, minting would never end
+    fn assert_feasible(&self, digest_bits: usize) {
+        if !matches!(self.target, Target::Threshold(_))
+            && usize::from(self.requested_bits) > digest_bits
+        {
+            panic!(
+                "difficulty of {} bits exceeds the {}-bit digest width, see `Stamp::try_new`",
+                self.requested_bits, digest_bits
+            );
+        }
+    }
+
+    /// Mints until solved, `cancel` is set or `max_iterations` counters were tried.
+    /// An unsolved stamp holds the next untried counter so minting can be resumed.
+    /// A difficulty beyond the digest's width is `Exhausted` without any attempt.
+    #[must_use = "mint consumes the stamp, dropping the result discards the work"]
+    pub fn mint_with(
+        mut self,
+        max_iterations: Option<usize>,
+        cancel: Option<&AtomicBool>,
+    ) -> MintOutcome {
+        match self.search(max_iterations, cancel) {
+            SearchStop::Solved => MintOutcome::Solved(self),
+            SearchStop::Cancelled => MintOutcome::Cancelled(self),
+            SearchStop::Exhausted => MintOutcome::Exhausted(self),
+        }
+    }
+
+    // The minting loop shared by the consuming and in-place entrypoints
+    fn search(&mut self, max_iterations: Option<usize>, cancel: Option<&AtomicBool>) -> SearchStop {
+        let mut iterations = 0;
+        self.invalidate_digest();
+
+        // no digest has more zero bits than it is wide, the search would never end
+        let digest_bits = 8 * self.algorithm.output_len();
+        if !matches!(self.target, Target::Threshold(_))
+            && usize::from(self.requested_bits) > digest_bits
+        {
+            return SearchStop::Exhausted;
+        }
+
+        // only the counter changes, so the payload up to it is serialized once
+        let mut payload = self.hashed_payload();
+        let prefix_len = payload.len() - self.counter_base64().len();
+        loop {
+            payload.truncate(prefix_len);
+            payload.extend(fields::encode_counter(self.counter, &self.format).as_bytes());
+            let digest = Stamp::hash(self, self.algorithm, &payload);
+            if self.meets_target(&digest) {
+                self.digest_cache = OnceLock::from(digest);
+                self.dirty = false;
+                return SearchStop::Solved;
+            }
+
+            self.counter = self.counter.wrapping_add(self.counter_step);
+
+            if cancel.is_some_and(|cancel| cancel.load(Ordering::Relaxed)) {
+                return SearchStop::Cancelled;
+            }
+
+            iterations += 1;
+            if max_iterations.is_some_and(|max_iterations| iterations >= max_iterations) {
+                return SearchStop::Exhausted;
+            }
+        }
+    }
+
+    /// Copy with a different difficulty and the counter reset, keeping salt,
+    /// resource and date, e.g. to compare minting cost across difficulties.
+    pub fn clone_with_bits(&self, bits: u8) -> Stamp {
+        let mut stamp = self.clone();
+        stamp.requested_bits = bits;
+        stamp.counter = 0;
+        stamp.invalidate_digest_since(self.proof_inputs());
+        stamp
+    }
+
+    /// Raises the difficulty and mints again, keeping salt, resource and date.
+    ///
+    /// The bits are part of the hashed payload, so the old solution never carries
+    /// over, the search just continues after the current counter. Lowering the
+    /// bits needs a re-mint as well, albeit a cheaper one. The creation date is
+    /// kept, so an old stamp may need a fresh date to not expire.
+    #[must_use = "mint consumes the stamp, dropping the result discards the work"]
+    pub fn bump_bits(mut self, new_bits: u8) -> Self {
+        self.requested_bits = new_bits;
+        self.counter = self.counter.wrapping_add(self.counter_step);
+        self.mint()
+    }
+
+    /// Re-mints an expired stamp dated now with the counter reset, returns a
+    /// stamp that is still valid unchanged. Keeps a long-lived stamp ready to use.
+    #[must_use = "mint consumes the stamp, dropping the result discards the work"]
+    pub fn remint_if_expired(mut self, expiry_duration: &Duration) -> Self {
+        if !self.is_expired(expiry_duration) {
+            return self;
+        }
+
+        self.creation_date = chrono::offset::Utc::now();
+        self.counter = 0;
+        self.mint()
+    }
+
+    /// Mints until solved or `deadline` passes, then returns `Exhausted`.
+    /// The clock is only read every few thousand attempts to keep the overhead low.
+    #[must_use = "mint consumes the stamp, dropping the result discards the work"]
+    pub fn mint_until(self, deadline: Instant) -> MintOutcome {
+        let mut stamp = self;
+        loop {
+            match stamp.mint_with(Some(DEADLINE_CHECK_INTERVAL), None) {
+                MintOutcome::Exhausted(unsolved) if Instant::now() < deadline => stamp = unsolved,
+                outcome => return outcome,
+            }
+        }
+    }
+
+    /// Mints with a caller supplied digest function instead of the stamp's algorithm,
+    /// e.g. BLAKE3 or a keyed hash. Rounds are not applied, the hasher does all the hashing.
+    /// Such stamps only pass `check_with_hasher` with the same hasher.
+    ///
+    /// Panics like `mint` if the difficulty exceeds the width of the hasher's digest.
+    #[must_use = "mint consumes the stamp, dropping the result discards the work"]
+    pub fn mint_with_hasher(mut self, hasher: impl Fn(&[u8]) -> Vec<u8>) -> Self {
+        self.invalidate_digest();
+        let mut digest = hasher(&self.hashed_payload());
+        self.assert_feasible(8 * digest.len());
+        while !self.meets_target(&digest) {
+            self.counter = self.counter.wrapping_add(self.counter_step);
+            digest = hasher(&self.hashed_payload());
+        }
+
+        // the cache only ever holds the built-in digest, so it stays empty
+        self.dirty = false;
+        self
+    }
+
+    /// Checks resource, expiry and difficulty.
+    ///
+    /// The difficulty check hashes the stamp's own fields, so changing any of
+    /// them after minting (resource, bits, date, salt, ...) breaks the proof
+    /// even if the changed resource matches the one passed in here.
+    ///
+    /// The difficulty is the one the stamp claims, so a client can simply claim
+    /// fewer bits. The claimed `requested_bits` are informational, security comes
+    /// from the server's own threshold passed to `check_bits`.
+    #[deprecated(
+        note = "trusts the bits the stamp claims, use `check_bits` with the server's difficulty"
+    )]
+    #[must_use = "an unused check result doesn't reject anything"]
+    pub fn check(
+        &self,
+        resource: &str,
+        expiry_duration: &Duration,
+    ) -> Result<&Self, HashcashError> {
+        self.check_at(
+            resource,
+            expiry_duration,
+            self.requested_bits,
+            chrono::offset::Utc::now(),
+        )
+    }
+
+    /// Like `check_bits` as of `now`, e.g. to replay archived logs at their arrival time.
+    #[must_use = "an unused check result doesn't reject anything"]
+    pub fn check_at(
+        &self,
+        resource: &str,
+        expiry_duration: &Duration,
+        required_bits: u8,
+        now: DateTime<Utc>,
+    ) -> Result<&Self, HashcashError> {
+        if !self.resource_matches(resource) {
+            return Err(HashcashError::ResourceMismatch);
+        }
+
+        if self.is_expired_at(expiry_duration, now) {
+            return Err(HashcashError::Expired);
+        }
+
+        self.validate_bits(required_bits)?;
+
+        Ok(self)
+    }
+
+    /// Like `check` but with the difficulty demanded by the server, the stamp's
+    /// claimed bits are ignored. The bits are counted the way the stamp's target
+    /// counts them, a threshold target is its own difficulty and ignores them.
+    #[must_use = "an unused check result doesn't reject anything"]
+    pub fn check_bits(
+        &self,
+        resource: &str,
+        expiry_duration: &Duration,
+        required_bits: u8,
+    ) -> Result<&Self, HashcashError> {
+        self.check_at(
+            resource,
+            expiry_duration,
+            required_bits,
+            chrono::offset::Utc::now(),
+        )
+    }
+
+    /// Like `check_bits` for hot paths: writes the hashed payload into a single buffer
+    /// and hashes into a stack buffer, which is its only allocation. The digest isn't
+    /// cached on the stamp either.
+    pub fn check_fast(
+        &self,
+        resource: &str,
+        expiry_duration: &Duration,
+        required_bits: u8,
+    ) -> Result<&Self, HashcashError> {
+        if !self.resource_matches(resource) {
+            return Err(HashcashError::ResourceMismatch);
+        }
+
+        if self.is_expired(expiry_duration) {
+            return Err(HashcashError::Expired);
+        }
+
+        if self.dirty {
+            return Err(HashcashError::StaleProof);
+        }
+
+        if !matches!(self.target, Target::Threshold(_)) {
+            self.format
+                .limits
+                .check_bits(required_bits, self.algorithm)?;
+        }
+
+        let mut payload = Vec::with_capacity(self.hashed_payload_capacity());
+        self.write_hashed_payload(&mut payload);
+
+        let mut digest = [0u8; 32];
+        let len = self.algorithm.digest_into(&payload, &mut digest);
+        for _ in 1..self.rounds {
+            let previous = digest;
+            self.algorithm.digest_into(&previous[..len], &mut digest);
+        }
+
+        if !self.target.met(&digest[..len], required_bits) {
+            return Err(HashcashError::InsufficientBits);
+        }
+
+        Ok(self)
+    }
+
+    /// Like `check_bits` with the expiry in seconds, for callers not using chrono.
+    pub fn check_secs(
+        &self,
+        resource: &str,
+        expiry_secs: u64,
+        required_bits: u8,
+    ) -> Result<&Self, HashcashError> {
+        let expiry_secs = i64::try_from(expiry_secs).unwrap_or(i64::MAX);
+        let expiry_duration = Duration::try_seconds(expiry_secs).unwrap_or(Duration::max_value());
+        self.check_bits(resource, &expiry_duration, required_bits)
+    }
+
+    /// Like `check_bits` but accepts any resource of the window, e.g. today's and
+    /// yesterday's challenge when the resource rotates daily.
+    pub fn check_rotating(
+        &self,
+        resources_window: &[&str],
+        expiry_duration: &Duration,
+        required_bits: u8,
+    ) -> Result<&Self, HashcashError> {
+        if !resources_window
+            .iter()
+            .any(|resource| self.resource_matches(resource))
+        {
+            return Err(HashcashError::ResourceMismatch);
+        }
+
+        if self.is_expired(expiry_duration) {
+            return Err(HashcashError::Expired);
+        }
+
+        self.validate_bits(required_bits)?;
+
+        Ok(self)
+    }
+
+    /// Like `check_bits` with the expected resource computed lazily, e.g. from request
+    /// context. `expected` only runs once the stamp is known not to be expired.
+    pub fn check_fn(
+        &self,
+        expected: impl FnOnce() -> String,
+        expiry_duration: &Duration,
+        required_bits: u8,
+    ) -> Result<&Self, HashcashError> {
+        if self.is_expired(expiry_duration) {
+            return Err(HashcashError::Expired);
+        }
+
+        if !self.resource_matches(&expected()) {
+            return Err(HashcashError::ResourceMismatch);
+        }
+
+        self.validate_bits(required_bits)?;
+
+        Ok(self)
+    }
+
+    /// Like `check_bits` but only accepts stamps of the allowed format versions, e.g.
+    /// to phase out v0 stamps.
+    pub fn check_versions(
+        &self,
+        resource: &str,
+        expiry_duration: &Duration,
+        required_bits: u8,
+        allowed: &[FormatVersion],
+    ) -> Result<&Self, HashcashError> {
+        if !allowed.contains(&self.version) {
+            return Err(HashcashError::VersionNotAllowed);
+        }
+
+        self.check_bits(resource, expiry_duration, required_bits)
+    }
+
+    /// Like `check_bits` without the expiry, for one-time tokens that are checked right away.
+    pub fn check_no_expiry(
+        &self,
+        resource: &str,
+        required_bits: u8,
+    ) -> Result<&Self, HashcashError> {
+        if !self.resource_matches(resource) {
+            return Err(HashcashError::ResourceMismatch);
+        }
+
+        self.validate_bits(required_bits)?;
+
+        Ok(self)
+    }
+
+    /// Weighted signal instead of a pass/fail, e.g. for a spam scorer:
+    ///
+    /// `actual_zero_bits * freshness * match`, where `freshness` is 0 once expired
+    /// and otherwise `0.5 + 0.5 * remaining / expiry`, and `match` is 1 if the
+    /// resource matches and 0.25 if it doesn't.
+    pub fn score(&self, resource: &str, expiry_duration: &Duration) -> f32 {
+        if self.is_expired(expiry_duration) {
+            return 0.0;
+        }
+
+        let remaining = self.remaining(expiry_duration).num_milliseconds() as f32;
+        let expiry = expiry_duration.num_milliseconds() as f32;
+        let freshness = 0.5 + 0.5 * (remaining / expiry).min(1.0);
+
+        let resource_match = if self.resource_matches(resource) {
+            1.0
+        } else {
+            0.25
+        };
+
+        self.actual_zero_bits() as f32 * freshness * resource_match
+    }
+
+    /// Like `check_bits` but collects every failed requirement instead of stopping at the first.
+    pub fn check_all(
+        &self,
+        resource: &str,
+        expiry_duration: &Duration,
+        required_bits: u8,
+    ) -> Result<(), Vec<HashcashError>> {
+        let mut errors = Vec::new();
+
+        if !self.resource_matches(resource) {
+            errors.push(HashcashError::ResourceMismatch);
+        }
+
+        if self.is_expired(expiry_duration) {
+            errors.push(HashcashError::Expired);
+        }
+
+        if let Err(e) = self.validate_bits(required_bits) {
+            errors.push(e);
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Time elapsed since the stamp's creation date.
+    pub fn age(&self) -> Duration {
+        self.age_at(chrono::offset::Utc::now())
+    }
+
+    pub fn age_at(&self, now: DateTime<Utc>) -> Duration {
+        now - self.creation_date
+    }
+
+    /// The instant from which the stamp counts as expired.
+    pub fn expires_at(&self, expiry_duration: &Duration) -> DateTime<Utc> {
+        // huge expiries saturate instead of overflowing the date
+        self.creation_date
+            .checked_add_signed(*expiry_duration)
+            .unwrap_or(DateTime::<Utc>::MAX_UTC)
+    }
+
+    pub fn is_expired(&self, expiry_duration: &Duration) -> bool {
+        self.is_expired_at(expiry_duration, chrono::offset::Utc::now())
+    }
+
+    pub fn is_expired_at(&self, expiry_duration: &Duration, now: DateTime<Utc>) -> bool {
+        now >= self.expires_at(expiry_duration)
+    }
+
+    /// Time left until `expires_at`, zero once the stamp has expired.
+    pub fn remaining(&self, expiry_duration: &Duration) -> Duration {
+        let remaining = self.expires_at(expiry_duration) - chrono::offset::Utc::now();
+        remaining.max(Duration::zero())
+    }
+
+    /// Compares only the resource field, without hashing the stamp.
+    pub fn resource_matches(&self, resource: &str) -> bool {
+        self.resource == resource
+    }
+
+    /// Checks only that the stamp's hash meets its own `requested_bits`,
+    /// without a resource or expiry. Useful as a pre-flight check before sending.
+    pub fn validate_self(&self) -> Result<(), HashcashError> {
+        self.validate_bits(self.requested_bits)
+    }
+
+    // The proof check of every `check_*`: the digest meets `bits` under the stamp's target
+    pub(crate) fn validate_bits(&self, bits: u8) -> Result<(), HashcashError> {
+        if self.dirty {
+            return Err(HashcashError::StaleProof);
+        }
+
+        // a threshold target doesn't use the bits
+        if !matches!(self.target, Target::Threshold(_)) {
+            self.format.limits.check_bits(bits, self.algorithm)?;
+        }
+
+        if !self.target.met(self.digest(), bits) {
+            return Err(HashcashError::InsufficientBits);
+        }
+
+        Ok(())
+    }
+
+    /// For debugging a failed check: the other algorithm the proof holds under, if
+    /// any, meaning the stamp was minted with it but its `alg` marker says otherwise.
+    /// Hashes once per algorithm, so it's not meant for every untrusted stamp.
+    pub fn minted_with_other_algorithm(&self) -> Option<Algorithm> {
+        let payload = self.hashed_payload();
+        Algorithm::ALL
+            .into_iter()
+            .filter(|algorithm| *algorithm != self.algorithm)
+            .find(|algorithm| self.meets_target(&self.hash(*algorithm, &payload)))
+    }
+
+    /// Whether the current fields hash to a digest meeting the stamp's own target,
+    /// ignoring resource, expiry and whether the stamp was changed since minting.
+    pub fn minted(&self) -> bool {
+        self.meets_target(self.digest())
+    }
+
+    /// Like `check_bits` but also requires the stamp to be marked with `algorithm`, a
+    /// client minting with another one fails with `AlgorithmMismatch`.
+    pub fn check_with_algorithm(
+        &self,
+        resource: &str,
+        expiry_duration: &Duration,
+        required_bits: u8,
+        algorithm: Algorithm,
+    ) -> Result<&Self, HashcashError> {
+        if self.algorithm != algorithm {
+            return Err(HashcashError::AlgorithmMismatch);
+        }
+
+        self.check_bits(resource, expiry_duration, required_bits)
+    }
+
+    /// Like `check_bits` for stamps minted with `mint_with_hasher`.
+    pub fn check_with_hasher(
+        &self,
+        resource: &str,
+        expiry_duration: &Duration,
+        required_bits: u8,
+        hasher: impl Fn(&[u8]) -> Vec<u8>,
+    ) -> Result<&Self, HashcashError> {
+        if !self.resource_matches(resource) {
+            return Err(HashcashError::ResourceMismatch);
+        }
+
+        if self.is_expired(expiry_duration) {
+            return Err(HashcashError::Expired);
+        }
+
+        if self.dirty {
+            return Err(HashcashError::StaleProof);
+        }
+
+        if !self
+            .target
+            .met(&hasher(&self.hashed_payload()), required_bits)
+        {
+            return Err(HashcashError::InsufficientBits);
+        }
+
+        Ok(self)
+    }
+
+    fn meets_target(&self, digest: &[u8]) -> bool {
+        self.target.met(digest, self.requested_bits)
+    }
+}
+
+// `%Y` as chrono writes it, years outside 0..=9999 get a sign
+fn write_year(out: &mut impl io::Write, year: i32) -> io::Result<()> {
+    if (0..10_000).contains(&year) {
+        write!(out, "{:04}", year)
+    } else {
+        write!(out, "{:+05}", year)
+    }
+}
+
+// RFC 2104 with SHA-256's 64-byte block size
+fn hmac_sha256(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut block = [0u8; 64];
+    if key.len() > block.len() {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = Sha256::new();
+    inner.update(block.map(|byte| byte ^ 0x36));
+    inner.update(message);
+
+    let mut outer = Sha256::new();
+    outer.update(block.map(|byte| byte ^ 0x5c));
+    outer.update(inner.finalize());
+    outer.finalize().to_vec()
+}
+
+fn parse_precise_date(value: &str) -> Result<DateTime<Utc>, HashcashError> {
+    match NaiveDateTime::parse_from_str(value, PRECISE_DATE_FORMAT) {
+        Ok(v) => Ok(v.and_utc()),
+        Err(_) => Err(HashcashError::InvalidDate),
+    }
+}
+
+fn random_salt() -> Vec<u8> {
+    with_rng(|rng| (0..16).map(|_| rng.gen()).collect())
+}
+
+#[cfg(feature = "testing")]
+thread_local! {
+    static SEEDED_RNG: std::cell::RefCell<Option<rand::rngs::StdRng>> =
+        const { std::cell::RefCell::new(None) };
+}
+
+/// Makes salts and random counters of the current thread reproducible from
+/// `seed`, `None` goes back to `thread_rng`. Only for tests, production always
+/// uses `thread_rng`. Creation dates still come from the clock.
+#[cfg(feature = "testing")]
+pub fn seed_rng(seed: Option<u64>) {
+    use rand::SeedableRng;
+
+    SEEDED_RNG.with(|rng| *rng.borrow_mut() = seed.map(rand::rngs::StdRng::seed_from_u64));
+}
+
+// Every random value of the crate is drawn through here
+fn with_rng<T>(f: impl FnOnce(&mut dyn RngCore) -> T) -> T {
+    #[cfg(feature = "testing")]
+    return SEEDED_RNG.with(|seeded| match seeded.borrow_mut().as_mut() {
+        Some(rng) => f(rng),
+        None => f(&mut rand::thread_rng()),
+    });
+
+    #[cfg(not(feature = "testing"))]
+    f(&mut rand::thread_rng())
+}
+
+// The precision and year width of a v0 or v1 date field, from its width
+fn date_layout(value: &str) -> Option<(DatePrecision, bool)> {
+    match value.len() {
+        6 => Some((DatePrecision::Days, false)),
+        10 => Some((DatePrecision::Minutes, false)),
+        12 => Some((DatePrecision::Seconds, false)),
+        14 => Some((DatePrecision::Seconds, true)),
+        _ => None,
+    }
+}
+
+fn parse_date(value: &str, format: &StampFormat) -> Result<DateTime<Utc>, HashcashError> {
+    let date = match date_layout(value) {
+        // the year is taken as written, so the pivot doesn't apply
+        Some((_, true)) => NaiveDateTime::parse_from_str(value, LONG_DATE_FORMAT).ok(),
+        Some((precision, false)) => {
+            let date = match precision {
+                DatePrecision::Days => NaiveDate::parse_from_str(value, DAY_DATE_FORMAT)
+                    .ok()
+                    .and_then(|date| date.and_hms_opt(0, 0, 0)),
+                DatePrecision::Minutes => {
+                    NaiveDateTime::parse_from_str(value, MINUTE_DATE_FORMAT).ok()
+                }
+                DatePrecision::Seconds => NaiveDateTime::parse_from_str(value, DATE_FORMAT).ok(),
+            };
+
+            date.and_then(|date| {
+                let two_digit_year = date.year() % 100;
+                let year = if two_digit_year < i32::from(format.century_pivot) {
+                    2000 + two_digit_year
+                } else {
+                    1900 + two_digit_year
+                };
+                date.with_year(year)
+            })
+        }
+        None => None,
+    };
+
+    match date.and_then(|date| date.and_local_timezone(format.date_offset).single()) {
+        Some(v) => Ok(v.with_timezone(&Utc)),
+        None => Err(HashcashError::InvalidDate),
+    }
+}
+
+// Algorithm named by the `alg` extension key, SHA-1 when absent
+fn extension_algorithm(extension: Option<&str>) -> Result<Algorithm, HashcashError> {
+    match extension.and_then(|extension| extension_value(extension, ALGORITHM_EXTENSION_KEY)) {
+        Some(value) => Algorithm::try_from(value),
+        None => Ok(Algorithm::Sha1),
+    }
+}
+
+// Hash rounds named by the `rounds` extension key, a single round when absent
+fn extension_rounds(extension: Option<&str>) -> Result<u32, HashcashError> {
+    match extension.and_then(|extension| extension_value(extension, ROUNDS_EXTENSION_KEY)) {
+        Some(value) => match value.parse::<u32>() {
+            Ok(v) if v >= 1 => Ok(v),
+            _ => Err(HashcashError::InvalidRounds),
+        },
+        None => Ok(1),
+    }
+}
+
+/// Number of zero bits before the first set bit, e.g. of a digest for custom
+/// proof of work schemes. All-zero input yields `8 * bytes.len()`.
+pub fn count_leading_zero_bits(bytes: &[u8]) -> u32 {
+    let mut bits = 0;
+    for byte in bytes {
+        bits += byte.leading_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+    bits
+}
+
+fn trailing_zero_bits(digest: &[u8]) -> u32 {
+    let mut bits = 0;
+    for byte in digest.iter().rev() {
+        bits += byte.trailing_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+    bits
+}
+
+fn to_hex(digest: &[u8]) -> String {
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn to_binary(digest: &[u8]) -> String {
+    digest.iter().map(|byte| format!("{:08b}", byte)).collect()
+}
+
+// Looks up `key` in an extension of the form `name1=value1;name2=value2`
+fn extension_value<'e>(extension: &'e str, key: &str) -> Option<&'e str> {
+    extension
+        .split(';')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(name, _)| *name == key)
+        .map(|(_, value)| value)
+}
+
+// Replaces (or removes when `value` is None) `key` in the extension. The pairs
+// are sorted by key afterwards, so the same pairs always serialize the same
+// regardless of the order they were set in.
+fn set_extension_value(extension: &str, key: &str, value: Option<&str>) -> String {
+    let mut pairs: Vec<String> = extension
+        .split(';')
+        .filter(|pair| !pair.is_empty() && pair.split('=').next() != Some(key))
+        .map(str::to_string)
+        .collect();
+
+    if let Some(value) = value {
+        pairs.push(format!("{}={}", key, value));
+    }
+
+    pairs.sort_by(|a, b| a.split('=').next().cmp(&b.split('=').next()));
+    pairs.join(";")
+}
+
+impl TryFrom<String> for Stamp {
+    type Error = HashcashError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Stamp::parse_with_format(&value, StampFormat::default())
+    }
+}
+
+impl TryFrom<&[u8]> for Stamp {
+    type Error = HashcashError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        let format = StampFormat::default();
+        StampFields::parse_bytes(value, &format)?.to_stamp_with_format(format)
+    }
+}
+
+// `{}` is the wire form, `{:#}` a short summary for log lines
+impl Display for Stamp {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if !f.alternate() {
+            return f.write_str(&self.canonical_string());
+        }
+
+        let resource = match self.resource.char_indices().nth(RESOURCE_SUMMARY_LEN) {
+            Some((end, _)) => format!("{}…", &self.resource[..end]),
+            None => self.resource.clone(),
+        };
+
+        let age = self.age().num_seconds().max(0);
+        let age = match age {
+            0..=59 => format!("{}s", age),
+            60..=3599 => format!("{}m", age / 60),
+            3600..=86399 => format!("{}h", age / 3600),
+            _ => format!("{}d", age / 86400),
+        };
+
+        write!(
+            f,
+            "hashcash(bits={}, res={}, age={})",
+            self.requested_bits, resource, age
+        )
+    }
+}
+
+impl From<Stamp> for String {
+    fn from(stamp: Stamp) -> Self {
+        stamp.to_string()
+    }
+}
+
+// Stamps are equal when their hashed wire forms are, e.g. for a `HashSet<Stamp>`
+// of spent stamps
+// Stamps are equal if they hold the same proof, an unhashed extension doesn't tell
+// them apart
+impl PartialEq for Stamp {
+    fn eq(&self, other: &Self) -> bool {
+        self.proof_inputs() == other.proof_inputs()
+    }
+}
+
+impl Eq for Stamp {}
+
+impl Hash for Stamp {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.proof_inputs().hash(state);
+    }
+}
+
+#[cfg(test)]
+#[allow(deprecated)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    type StampParams = (FormatVersion, u8, String, Option<String>);
+
+    // Random but small configurations, so every case mints quickly. `Stamp` isn't
+    // `Debug`, so the strategy yields its parameters for proptest to report.
+    fn stamp_params() -> impl Strategy<Value = StampParams> {
+        (
+            prop::sample::select(vec![
+                FormatVersion::V0,
+                FormatVersion::V1,
+                FormatVersion::V2,
+            ]),
+            1..=10u8,
+            "[a-z]{1,23}",
+            prop::option::of(Just("k=v".to_string())),
+        )
+    }
+
+    fn stamp_from((version, bits, resource, extension): StampParams) -> Stamp {
+        Stamp::new(version, bits, resource, extension)
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn minted_stamps_meet_their_difficulty(params in stamp_params()) {
+            let minted = stamp_from(params).mint();
+            prop_assert!(minted.difficulty_met(minted.requested_bits()));
+        }
+
+        #[test]
+        fn check_accepts_freshly_minted_stamps(params in stamp_params()) {
+            let minted = stamp_from(params).mint();
+            let (resource, bits) = (minted.resource.clone(), minted.requested_bits());
+            prop_assert!(minted.check_bits(&resource, &Duration::days(365), bits).is_ok());
+        }
+
+        #[test]
+        fn minted_stamps_round_trip(params in stamp_params()) {
+            let minted = stamp_from(params).mint();
+            let parsed = Stamp::try_from(minted.to_string()).unwrap();
+            prop_assert!(parsed == minted);
+            prop_assert_eq!(parsed.to_string(), minted.to_string());
+        }
+    }
+
+    // Changes a field behind the setters' back, as an attacker editing the wire form would
+    fn tampered(stamp: &Stamp, tamper: impl FnOnce(&mut Stamp)) -> Result<(), HashcashError> {
+        let mut stamp = stamp.clone();
+        tamper(&mut stamp);
+        stamp.digest_cache = OnceLock::new();
+        stamp.dirty = false;
+        stamp.validate_self()
+    }
+
+    #[test]
+    fn tampering_with_hashed_fields_breaks_the_proof() {
+        let minted =
+            Stamp::new(FormatVersion::V1, 16, "alice@example.org".to_string(), None).mint();
+        assert!(minted.check("alice@example.org", &DEFAULT_EXPIRY).is_ok());
+
+        let resource = tampered(&minted, |stamp| {
+            stamp.resource = "mallory@example.org".to_string()
+        });
+        assert_eq!(resource, Err(HashcashError::InsufficientBits));
+
+        let bits = tampered(&minted, |stamp| stamp.requested_bits = 17);
+        assert!(bits.is_err());
+
+        let date = tampered(&minted, |stamp| stamp.creation_date += Duration::seconds(1));
+        assert_eq!(date, Err(HashcashError::InsufficientBits));
+
+        let salt = tampered(&minted, |stamp| stamp.salt[0] ^= 1);
+        assert_eq!(salt, Err(HashcashError::InsufficientBits));
+    }
+
+    #[test]
+    fn rounds_are_part_of_the_proof() {
+        let minted = Stamp::new(FormatVersion::V1, 16, "r".to_string(), None)
+            .with_rounds(10)
+            .mint();
+        assert_eq!(minted.rounds(), 10);
+        assert!(minted.check_bits("r", &DEFAULT_EXPIRY, 16).is_ok());
+
+        let parsed = Stamp::try_from(minted.to_string()).unwrap();
+        assert_eq!(parsed.rounds(), 10);
+        assert!(parsed.check_bits("r", &DEFAULT_EXPIRY, 16).is_ok());
+
+        // the same fields hashed once
+        let once = tampered(&minted, |stamp| stamp.rounds = 1);
+        assert_eq!(once, Err(HashcashError::InsufficientBits));
+    }
+
+    #[test]
+    fn v0_stamps_ignore_algorithm_and_rounds() {
+        let minted = Stamp::new(FormatVersion::V0, 8, "r".to_string(), None)
+            .with_algorithm(Algorithm::Sha256)
+            .with_rounds(3)
+            .mint();
+        assert_eq!(minted.algorithm(), Algorithm::Sha1);
+        assert_eq!(minted.rounds(), 1);
+
+        let parsed = Stamp::try_from(minted.to_string()).unwrap();
+        assert!(parsed.check_no_expiry("r", 8).is_ok());
+    }
+
+    #[test]
+    fn unchanged_hashed_fields_keep_the_proof() {
+        let minted = Stamp::new(FormatVersion::V1, 8, "r".to_string(), None).mint();
+        let parsed = Stamp::try_from(minted.to_string()).unwrap();
+
+        let same = parsed
+            .clone()
+            .with_format(StampFormat::default())
+            .with_algorithm(Algorithm::Sha1)
+            .with_rounds(1)
+            .with_counter(minted.counter);
+        assert!(!same.is_dirty());
+        assert!(same.check("r", &DEFAULT_EXPIRY).is_ok());
+
+        let changed = parsed.with_counter(minted.counter + 1);
+        assert!(changed.is_dirty());
+        assert_eq!(
+            changed.check("r", &DEFAULT_EXPIRY).err(),
+            Some(HashcashError::StaleProof)
+        );
+    }
+
+    #[test]
+    fn reports_the_algorithm_a_stamp_was_minted_with() {
+        let minted = Stamp::new(FormatVersion::V1, 16, "r".to_string(), None).mint();
+        assert_eq!(minted.minted_with_other_algorithm(), None);
+
+        let mut marked = minted.clone();
+        marked.algorithm = Algorithm::Sha256;
+        marked.digest_cache = OnceLock::new();
+        assert_eq!(marked.validate_self(), Err(HashcashError::InsufficientBits));
+        assert_eq!(marked.minted_with_other_algorithm(), Some(Algorithm::Sha1));
+
+        let checked = minted.check_with_algorithm("r", &DEFAULT_EXPIRY, 16, Algorithm::Sha256);
+        assert_eq!(checked.err(), Some(HashcashError::AlgorithmMismatch));
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn fast_payload_matches_the_hashed_payload(
+            params in stamp_params(),
+            format in 0..5usize,
+            counter in any::<usize>(),
+            secret_len in 0..3usize,
+        ) {
+            let formats = [
+                StampFormat::default(),
+                StampFormat::reference(),
+                StampFormat {
+                    separator: '¦',
+                    percent_encode_resource: true,
+                    hash_extension: false,
+                    ..StampFormat::default()
+                },
+                StampFormat {
+                    four_digit_year: true,
+                    field_encoding: FieldEncoding::Base32,
+                    date_offset: FixedOffset::east_opt(2 * 3600).unwrap(),
+                    ..StampFormat::default()
+                },
+                StampFormat {
+                    date_precision: DatePrecision::Minutes,
+                    ..StampFormat::default()
+                },
+            ];
+
+            let stamp = stamp_from(params);
+            let resource = format!("{} @{}", stamp.resource, counter % 100);
+            let stamp = stamp
+                .with_resource(resource)
+                .with_format(formats[format])
+                .with_counter(counter)
+                .with_server_secret(&vec![0xff; secret_len]);
+            let mut payload = Vec::with_capacity(stamp.hashed_payload_capacity());
+            stamp.write_hashed_payload(&mut payload);
+
+            prop_assert_eq!(&payload, &stamp.hashed_payload());
+            prop_assert!(payload.len() <= stamp.hashed_payload_capacity());
+
+            let mut written = Vec::new();
+            stamp.write_to(&mut written).unwrap();
+            prop_assert_eq!(written, stamp.canonical_bytes());
+        }
+    }
+
+    #[test]
+    fn minting_and_checking_must_agree_on_hashing_the_extension() {
+        let hashed = StampFormat::default();
+        let excluded = StampFormat {
+            hash_extension: false,
+            ..StampFormat::default()
+        };
+
+        for (minted_with, checked_with) in [(hashed, excluded), (excluded, hashed)] {
+            let minted = Stamp::new(FormatVersion::V1, 16, "r".to_string(), Some("k=v".into()))
+                .with_format(minted_with)
+                .mint();
+            let line = minted.to_string();
+
+            let agreeing = Stamp::parse_with_format(&line, minted_with).unwrap();
+            assert!(agreeing.check_bits("r", &DEFAULT_EXPIRY, 16).is_ok());
+
+            let disagreeing = Stamp::parse_with_format(&line, checked_with).unwrap();
+            assert_eq!(disagreeing.to_string(), line);
+            assert_eq!(
+                disagreeing.check_bits("r", &DEFAULT_EXPIRY, 16).err(),
+                Some(HashcashError::InsufficientBits)
+            );
+        }
+    }
+
+    #[test]
+    fn extension_pairs_serialize_independent_of_their_order() {
+        let stamp = Stamp::new(FormatVersion::V1, 8, "r".to_string(), None);
+        let forward = stamp
+            .clone()
+            .with_rounds(2)
+            .with_extension_pair("b", "2")
+            .and_then(|stamp| stamp.with_extension_pair("a", "1"))
+            .and_then(|stamp| stamp.with_extension_pair("c", "3"))
+            .unwrap();
+        let backward = stamp
+            .with_extension_pair("c", "3")
+            .and_then(|stamp| stamp.with_extension_pair("a", "1"))
+            .and_then(|stamp| stamp.with_extension_pair("b", "2"))
+            .unwrap()
+            .with_rounds(2);
+
+        assert_eq!(forward.extension.as_deref(), Some("a=1;b=2;c=3;rounds=2"));
+        assert_eq!(forward.canonical_string(), backward.canonical_string());
+        assert_eq!(forward.hashed_payload(), backward.hashed_payload());
+    }
+
+    #[test]
+    fn every_algorithm_round_trips() {
+        for version in [FormatVersion::V1, FormatVersion::V2] {
+            for algorithm in Algorithm::ALL {
+                let minted = Stamp::new(version, 12, "r".to_string(), None)
+                    .with_algorithm(algorithm)
+                    .mint();
+                let parsed = Stamp::try_from(minted.to_string()).unwrap();
+
+                assert_eq!(parsed.algorithm(), algorithm);
+                assert!(parsed == minted);
+                assert!(parsed.check("r", &DEFAULT_EXPIRY).is_ok());
+            }
+        }
+
+        // no marker means SHA-1
+        let minted = Stamp::new(FormatVersion::V1, 12, "r".to_string(), None).mint();
+        assert!(!minted.to_string().contains("alg="));
+        let parsed = Stamp::try_from(minted.to_string()).unwrap();
+        assert_eq!(parsed.algorithm(), Algorithm::Sha1);
+    }
+
+    // Only compiles because resource and expiry don't share a lifetime with each
+    // other or with the result, which borrows just the stamp
+    #[test]
+    fn check_borrows_resource_and_expiry_independently() {
+        let stamp = Stamp::new(FormatVersion::V1, 8, "r".to_string(), None).mint();
+        let expiry = DEFAULT_EXPIRY;
+
+        let checked = {
+            let resource = String::from("r");
+            stamp.check(&resource, &expiry).unwrap()
+        };
+        let checked_again = {
+            let expiry = Duration::days(1);
+            checked.check(&stamp.resource, &expiry).unwrap()
+        };
+
+        assert!(checked_again == &stamp);
+    }
+
+    #[test]
+    fn two_digit_years_follow_the_century_pivot() {
+        let year = |value: &str, century_pivot: u8| {
+            let format = StampFormat {
+                century_pivot,
+                ..StampFormat::default()
+            };
+            parse_date(value, &format).unwrap().year()
+        };
+
+        assert_eq!(year("690101000000", 70), 2069);
+        assert_eq!(year("680101000000", 70), 2068);
+        assert_eq!(year("700101000000", 70), 1970);
+
+        assert_eq!(year("690101000000", 69), 1969);
+        assert_eq!(year("680101000000", 69), 2068);
+        assert_eq!(year("680101000000", 0), 1968);
+    }
+
+    #[test]
+    fn four_digit_years_round_trip() {
+        let line = "1:8:20240525120406:r::c2FsdA==:MA==";
+        let parsed = Stamp::try_from(line.to_string()).unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 5, 25)
+            .unwrap()
+            .and_hms_opt(12, 4, 6)
+            .unwrap()
+            .and_utc();
+        assert_eq!(parsed.creation_date, date);
+        assert!(parsed.format.four_digit_year);
+        assert_eq!(parsed.date_field(), "20240525120406");
+        assert_eq!(parsed.to_string(), line);
+        assert_eq!(parsed.hashed_payload(), line.as_bytes());
+
+        // the year is taken as written, the century pivot doesn't apply
+        let old = Stamp::try_from("1:8:19690101000000:r::c2FsdA==:MA==".to_string()).unwrap();
+        assert_eq!(old.creation_date.year(), 1969);
+
+        let format = StampFormat {
+            four_digit_year: true,
+            ..StampFormat::default()
+        };
+        let minted = Stamp::new(FormatVersion::V1, 8, "r".to_string(), None)
+            .with_format(format)
+            .mint();
+        assert_eq!(minted.date_field().len(), 14);
+
+        let parsed = Stamp::try_from(minted.to_string()).unwrap();
+        assert_eq!(parsed.to_string(), minted.to_string());
+        assert!(parsed.check("r", &DEFAULT_EXPIRY).is_ok());
+        assert!(parsed.check_bits("r", &DEFAULT_EXPIRY, 8).is_ok());
+    }
+
+    #[test]
+    fn stamps_expire_exactly_at_expires_at() {
+        let stamp = Stamp::new(FormatVersion::V1, 8, "r".to_string(), None).mint();
+        let expiry = Duration::hours(1);
+        let expires_at = stamp.expires_at(&expiry);
+        assert_eq!(expires_at, stamp.creation_date + expiry);
+
+        assert!(!stamp.is_expired_at(&expiry, expires_at - Duration::nanoseconds(1)));
+        assert!(stamp.is_expired_at(&expiry, expires_at));
+        assert!(stamp.is_expired_at(&expiry, expires_at + Duration::nanoseconds(1)));
+        assert_eq!(
+            stamp.check_at("r", &expiry, 8, expires_at).err(),
+            Some(HashcashError::Expired)
+        );
+
+        assert_eq!(
+            stamp.expires_at(&Duration::max_value()),
+            DateTime::<Utc>::MAX_UTC
+        );
+    }
+
+    #[test]
+    fn empty_and_missing_extensions_round_trip_alike() {
+        for extension in [Some(String::new()), None] {
+            let minted = Stamp::new(FormatVersion::V1, 8, "r".to_string(), extension).mint();
+            assert_eq!(minted.extension, None);
+
+            let line = minted.to_string();
+            let parsed = Stamp::try_from(line.clone()).unwrap();
+            assert_eq!(parsed.extension, None);
+            assert_eq!(parsed.to_string(), line);
+            assert!(parsed.check("r", &DEFAULT_EXPIRY).is_ok());
+        }
+    }
+
+    #[test]
+    fn parses_dates_written_at_plus_two_hours() {
+        let format = StampFormat {
+            date_offset: FixedOffset::east_opt(2 * 3600).unwrap(),
+            ..StampFormat::default()
+        };
+        let parsed = Stamp::parse_with_format("1:8:260105113015:r::c2FsdA==:MA==", format).unwrap();
+        let utc = NaiveDate::from_ymd_opt(2026, 1, 5)
+            .unwrap()
+            .and_hms_opt(9, 30, 15)
+            .unwrap()
+            .and_utc();
+        assert_eq!(parsed.creation_date, utc);
+        assert_eq!(parsed.to_string(), "1:8:260105113015:r::c2FsdA==:MA==");
+
+        let minted = Stamp::new(FormatVersion::V1, 8, "r".to_string(), None)
+            .with_format(format)
+            .mint();
+        let created = minted.creation_date.with_nanosecond(0).unwrap();
+        let parsed = Stamp::parse_with_format(&minted.to_string(), format).unwrap();
+        assert_eq!(parsed.creation_date, created);
+        assert!(parsed.check("r", &DEFAULT_EXPIRY).is_ok());
+
+        // read as UTC the date is two hours late
+        let misread = Stamp::try_from(minted.to_string()).unwrap();
+        assert_eq!(misread.creation_date - created, Duration::hours(2));
+    }
+
+    #[test]
+    fn leading_and_trailing_targets_round_trip() {
+        let positions = [
+            (
+                Target::BitPrefix,
+                Target::BitSuffix,
+                count_leading_zero_bits as fn(&[u8]) -> u32,
+            ),
+            (Target::BitSuffix, Target::BitPrefix, trailing_zero_bits),
+        ];
+        for (target, other, zero_bits) in positions {
+            let minted = Stamp::new(FormatVersion::V1, 16, "r".to_string(), None)
+                .with_target(target)
+                .mint();
+            assert!(zero_bits(minted.digest()) >= 16);
+
+            let parsed = Stamp::try_from(minted.to_string())
+                .unwrap()
+                .with_target(target);
+            assert!(parsed.check("r", &DEFAULT_EXPIRY).is_ok());
+
+            // fails at the other end, but for a 1 in 2^16 chance
+            let other = parsed.with_target(other);
+            assert_eq!(other.validate_self(), Err(HashcashError::InsufficientBits));
+        }
+    }
+
+    #[test]
+    fn derived_salt_is_deterministic() {
+        let derive = |secret: &[u8], resource: &str| {
+            Stamp::new(FormatVersion::V1, 8, resource.to_string(), None)
+                .with_derived_salt(secret)
+                .salt
+        };
+
+        let salt = derive(b"secret", "alice@example.org");
+        assert_eq!(salt.len(), 16);
+        assert_eq!(salt, derive(b"secret", "alice@example.org"));
+        assert_eq!(salt, hmac_sha256(b"secret", b"alice@example.org")[..16]);
+
+        // RFC 4231 test case 2, so servers in other languages derive the same salt
+        assert_eq!(
+            to_hex(&hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+
+        assert_ne!(salt, derive(b"secret", "bob@example.org"));
+        assert_ne!(salt, derive(b"other secret", "alice@example.org"));
+    }
+
+    #[test]
+    fn counts_leading_zero_bits() {
+        assert_eq!(count_leading_zero_bits(&[]), 0);
+        assert_eq!(count_leading_zero_bits(&[0; 20]), 160);
+        assert_eq!(count_leading_zero_bits(&[0xff; 20]), 0);
+
+        assert_eq!(count_leading_zero_bits(&[0x80]), 0);
+        assert_eq!(count_leading_zero_bits(&[0x01]), 7);
+        assert_eq!(count_leading_zero_bits(&[0x00, 0x80]), 8);
+        assert_eq!(count_leading_zero_bits(&[0x00, 0x01]), 15);
+        assert_eq!(count_leading_zero_bits(&[0x00, 0x00, 0x7f, 0x00]), 17);
+
+        // zero bytes after the first set bit don't count
+        assert_eq!(count_leading_zero_bits(&[0x01, 0x00, 0x00]), 7);
+    }
+
+    #[test]
+    fn hashes_a_known_counter_to_a_pinned_digest() {
+        let line = "1:8:260105093015:r::c2FsdA==:MTAx";
+        let stamp = Stamp::try_from(line.to_string()).unwrap();
+        assert_eq!(stamp.counter, 5);
+        assert_eq!(stamp.hashed_payload(), line.as_bytes());
+        assert_eq!(
+            to_hex(stamp.digest()),
+            "4e879bb2541cc18ef7e084f5927be2768b6b0ec2"
+        );
+
+        // minting only replaces the counter tail of the payload
+        let unminted = stamp.clone().with_counter(0);
+        let mut payload = unminted.hashed_payload();
+        payload.truncate(payload.len() - unminted.counter_base64().len());
+        payload.extend(fields::encode_counter(5, &stamp.format).as_bytes());
+        assert_eq!(payload, line.as_bytes());
+    }
+
+    #[test]
+    fn impossible_difficulties_are_rejected_without_minting() {
+        let rejected = Stamp::try_new(FormatVersion::V1, 161, "r".to_string(), None);
+        assert_eq!(rejected.err(), Some(HashcashError::InvalidBits));
+        let rejected = Stamp::try_new(FormatVersion::V1, 0, "r".to_string(), None);
+        assert_eq!(rejected.err(), Some(HashcashError::InvalidBits));
+
+        let sha256 = Some("alg=sha256".to_string());
+        assert!(Stamp::try_new(FormatVersion::V1, 161, "r".to_string(), sha256).is_ok());
+
+        assert!(matches!(
+            Stamp::new(FormatVersion::V1, 161, "r".to_string(), None).mint_with(None, None),
+            MintOutcome::Exhausted(_)
+        ));
+    }
+
+    #[test]
+    #[should_panic(expected = "exceeds the 160-bit digest width")]
+    fn minting_an_impossible_difficulty_panics() {
+        let _ = Stamp::new(FormatVersion::V1, 161, "r".to_string(), None).mint();
+    }
+
+    #[test]
+    #[should_panic(expected = "exceeds the 16-bit digest width")]
+    fn minting_beyond_a_custom_digest_panics() {
+        let short = |payload: &[u8]| Algorithm::Sha1.digest(payload)[..2].to_vec();
+        let _ = Stamp::new(FormatVersion::V1, 17, "r".to_string(), None).mint_with_hasher(short);
+    }
+
+    #[test]
+    fn two_zero_bytes_pass_16_bits_and_fail_17() {
+        let minted = Stamp::new(FormatVersion::V1, 16, "r".to_string(), None)
+            .with_target(Target::BytePrefix)
+            .mint();
+        assert!(minted.digest()[..2].iter().all(|byte| *byte == 0));
+        assert!(minted.check("r", &DEFAULT_EXPIRY).is_ok());
+        assert!(minted.check_bits("r", &DEFAULT_EXPIRY, 16).is_ok());
+
+        // 23 leading zero bits are only 2 whole zero bytes
+        let digest = [0x00, 0x00, 0x01, 0xff];
+        let with_bits = |bits, target| Stamp {
+            requested_bits: bits,
+            target,
+            ..minted.clone()
+        };
+        assert!(with_bits(16, Target::BytePrefix).meets_target(&digest));
+        assert!(!with_bits(17, Target::BytePrefix).meets_target(&digest));
+        assert!(with_bits(17, Target::BitPrefix).meets_target(&digest));
+    }
+
+    #[test]
+    fn claimed_bits_above_the_work_fail_check_bits() {
+        let minted = Stamp::new(FormatVersion::V1, 8, "r".to_string(), None).mint();
+        let claimed = minted.to_string().replacen("1:8:", "1:24:", 1);
+        let claimed = Stamp::try_from(claimed).unwrap();
+        assert_eq!(claimed.bits(), 24);
+
+        // the edited stamp hashes to a new digest, which meets 16 bits only by a
+        // 1 in 2^16 chance
+        for required in [16, 24] {
+            assert_eq!(
+                claimed.check_bits("r", &DEFAULT_EXPIRY, required).err(),
+                Some(HashcashError::InsufficientBits)
+            );
+        }
+    }
+
+    #[test]
+    fn low_claims_cant_lower_the_servers_requirement() {
+        let minted = Stamp::new(FormatVersion::V1, 8, "r".to_string(), None).mint();
+        assert!(minted.check("r", &DEFAULT_EXPIRY).is_ok());
+        assert!(minted.check_bits("r", &DEFAULT_EXPIRY, 8).is_ok());
+
+        let required = 8 + minted.bits_surplus() as u8 + 1;
+        assert_eq!(
+            minted.check_bits("r", &DEFAULT_EXPIRY, required).err(),
+            Some(HashcashError::InsufficientBits)
+        );
+    }
+
+    #[test]
+    fn check_bits_counts_the_way_the_target_does() {
+        let with_digest = |target, digest: &[u8]| {
+            let mut stamp =
+                Stamp::new(FormatVersion::V1, 8, "r".to_string(), None).with_target(target);
+            let mut padded = digest.to_vec();
+            padded.resize(20, 0xff);
+            stamp.digest_cache = OnceLock::from(padded);
+            stamp
+        };
+        let passes = |stamp: &Stamp, bits| stamp.check_bits("r", &DEFAULT_EXPIRY, bits).is_ok();
+
+        // 23 leading zero bits
+        let prefix = [0x00, 0x00, 0x01];
+        let stamp = with_digest(Target::BitPrefix, &prefix);
+        assert!(passes(&stamp, 23) && !passes(&stamp, 24));
+
+        // 15 leading zero bits are 3 zero hex digits
+        let stamp = with_digest(Target::NibblePrefix, &[0x00, 0x01]);
+        assert!(passes(&stamp, 12) && !passes(&stamp, 13));
+
+        // 2 zero bytes
+        let stamp = with_digest(Target::BytePrefix, &prefix);
+        assert!(passes(&stamp, 16) && !passes(&stamp, 17));
+
+        // 23 trailing zero bits
+        let mut suffix = [0xff; 20];
+        suffix[17..].copy_from_slice(&[0x80, 0x00, 0x00]);
+        let stamp = with_digest(Target::BitSuffix, &suffix);
+        assert!(passes(&stamp, 23) && !passes(&stamp, 24));
+        assert!(!passes(&with_digest(Target::BitSuffix, &prefix), 8));
+
+        let mut threshold = [0; 20];
+        threshold[2] = 0x80;
+        let threshold = Target::Threshold(threshold);
+        let below = with_digest(threshold, &[0x00, 0x00, 0x7f]);
+        assert!(passes(&below, 8) && passes(&below, 100));
+        assert!(!passes(&with_digest(threshold, &[0x00, 0x00, 0x80]), 8));
+
+        // and what minting produced
+        for target in [
+            Target::BitPrefix,
+            Target::BitSuffix,
+            Target::NibblePrefix,
+            Target::BytePrefix,
+        ] {
+            let minted = Stamp::new(FormatVersion::V1, 16, "r".to_string(), None)
+                .with_target(target)
+                .mint();
+            assert!(minted.check_bits("r", &DEFAULT_EXPIRY, 16).is_ok());
+        }
+    }
+
+    #[test]
+    fn wrappers_demand_the_required_bits() {
+        let minted = Stamp::new(FormatVersion::V1, 8, "r".to_string(), None).mint();
+        let insufficient = Some(HashcashError::InsufficientBits);
+
+        assert!(verify(&minted.to_string(), "r", DEFAULT_EXPIRY, 8).is_ok());
+        assert_eq!(
+            verify(&minted.to_string(), "r", DEFAULT_EXPIRY, 40).err(),
+            insufficient
+        );
+        assert!(minted.check_fast("r", &DEFAULT_EXPIRY, 8).is_ok());
+        assert_eq!(
+            minted.check_fast("r", &DEFAULT_EXPIRY, 40).err(),
+            insufficient
+        );
+        assert_eq!(minted.check_secs("r", 3600, 40).err(), insufficient);
+        assert_eq!(minted.check_no_expiry("r", 40).err(), insufficient);
+        assert_eq!(
+            minted.check_all("r", &DEFAULT_EXPIRY, 40).err(),
+            Some(vec![HashcashError::InsufficientBits])
+        );
+        let sha1 = |payload: &[u8]| Algorithm::Sha1.digest(payload);
+        assert_eq!(
+            minted
+                .check_with_hasher("r", &DEFAULT_EXPIRY, 40, sha1)
+                .err(),
+            insufficient
+        );
+    }
+
+    #[test]
+    fn nibble_prefix_counts_whole_hex_zeros() {
+        let stamp = Stamp::new(FormatVersion::V1, 12, "r".to_string(), None)
+            .with_target(Target::NibblePrefix);
+        let with_bits = |bits| Stamp {
+            requested_bits: bits,
+            ..stamp.clone()
+        };
+
+        // 15 leading zero bits but only 3 zero hex digits
+        let digest = [0x00, 0x01, 0xff];
+        assert_eq!(to_hex(&digest), "0001ff");
+        assert!(with_bits(12).meets_target(&digest));
+        assert!(!with_bits(13).meets_target(&digest));
+        assert!(with_bits(0).meets_target(&[0xff]));
+        assert!(with_bits(24).meets_target(&[0x00, 0x00, 0x00]));
+    }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod seeded_tests {
+    use super::*;
+
+    fn salts_from(seed: Option<u64>) -> Vec<Vec<u8>> {
+        seed_rng(seed);
+        (0..3)
+            .map(|_| Stamp::new(FormatVersion::V1, 1, "r".to_string(), None).salt)
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_salts() {
+        let salts = salts_from(Some(7));
+        assert_eq!(salts, salts_from(Some(7)));
+        assert_ne!(salts, salts_from(Some(8)));
+        assert_ne!(salts[0], salts[1]);
+
+        assert_ne!(salts, salts_from(None));
+    }
+}
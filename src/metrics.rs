@@ -0,0 +1,85 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use chrono::Duration;
+
+use crate::{HashcashError, SpendStore, Stamp};
+
+// Callbacks for verification outcomes, e.g. to feed a Prometheus exporter
+pub trait Metrics {
+    fn on_accept(&self);
+    fn on_reject(&self, error: &HashcashError);
+    /// Called instead of `on_reject` for a stamp that was already spent.
+    fn on_replay(&self);
+}
+
+// Counts outcomes in atomics, expired stamps are counted apart from other rejections
+#[derive(Debug, Default)]
+pub struct AtomicMetrics {
+    pub accepted: AtomicU64,
+    pub rejected: AtomicU64,
+    pub expired: AtomicU64,
+    pub replayed: AtomicU64,
+}
+
+impl AtomicMetrics {
+    pub fn new() -> Self {
+        AtomicMetrics::default()
+    }
+}
+
+impl Metrics for AtomicMetrics {
+    fn on_accept(&self) {
+        self.accepted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn on_reject(&self, error: &HashcashError) {
+        match error {
+            HashcashError::Expired => self.expired.fetch_add(1, Ordering::Relaxed),
+            _ => self.rejected.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+
+    fn on_replay(&self) {
+        self.replayed.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl Stamp {
+    /// Like `check_bits`, reporting the outcome to `metrics`.
+    pub fn check_with_metrics(
+        &self,
+        resource: &str,
+        expiry_duration: &Duration,
+        required_bits: u8,
+        metrics: &impl Metrics,
+    ) -> Result<&Self, HashcashError> {
+        match self.check_bits(resource, expiry_duration, required_bits) {
+            Ok(stamp) => {
+                metrics.on_accept();
+                Ok(stamp)
+            }
+            Err(e) => {
+                metrics.on_reject(&e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Like `redeem`, reporting the outcome to `metrics`.
+    pub fn redeem_with_metrics(
+        &self,
+        resource: &str,
+        expiry_duration: &Duration,
+        required_bits: u8,
+        store: &impl SpendStore,
+        metrics: &impl Metrics,
+    ) -> Result<(), HashcashError> {
+        let result = self.redeem(resource, expiry_duration, required_bits, store);
+        match &result {
+            Ok(()) => metrics.on_accept(),
+            Err(HashcashError::AlreadySpent) => metrics.on_replay(),
+            Err(e) => metrics.on_reject(e),
+        }
+        result
+    }
+}
@@ -0,0 +1,330 @@
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+
+use chrono::Utc;
+
+use crate::{random_salt, Algorithm, FormatVersion, MintOutcome, Stamp, StampFormat};
+
+// Mints any number of stamps for one configuration, each with a fresh salt.
+// The configured stamp is built once, with its extension parsed and algorithm and
+// format applied, and each mint only draws a new salt and date on a copy of it.
+// Salt and date differ between stamps, so no hash state carries over.
+#[derive(Clone)]
+pub struct Miner {
+    version: FormatVersion,
+    requested_bits: u8,
+    resource: String,
+    extension: Option<String>,
+    // `None` keeps the algorithm an `alg=` extension selects
+    algorithm: Option<Algorithm>,
+    format: StampFormat,
+    template: Stamp,
+}
+
+impl Miner {
+    pub fn new(version: FormatVersion, requested_bits: u8, resource: String) -> Self {
+        let template = Stamp::new(version, requested_bits, resource.clone(), None);
+        Miner {
+            version,
+            requested_bits,
+            resource,
+            extension: None,
+            algorithm: None,
+            format: StampFormat::default(),
+            template,
+        }
+    }
+
+    pub fn with_extension(mut self, extension: String) -> Self {
+        self.extension = Some(extension);
+        self.rebuild()
+    }
+
+    pub fn with_algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = Some(algorithm);
+        self.rebuild()
+    }
+
+    pub fn with_format(mut self, format: StampFormat) -> Self {
+        self.format = format;
+        self.rebuild()
+    }
+
+    // Applies the configuration to the template once instead of on every mint
+    fn rebuild(mut self) -> Self {
+        let mut template = Stamp::new(
+            self.version,
+            self.requested_bits,
+            self.resource.clone(),
+            self.extension.clone(),
+        );
+        if let Some(algorithm) = self.algorithm {
+            template = template.with_algorithm(algorithm);
+        }
+
+        self.template = template.with_format(self.format);
+        self
+    }
+
+    /// Mints a new stamp with a fresh salt and the current date.
+    pub fn mint_next(&self) -> Stamp {
+        let mut stamp = self.template.clone();
+        stamp.salt = random_salt();
+        stamp.creation_date = Utc::now();
+        stamp.counter = 0;
+        stamp.mint()
+    }
+}
+
+impl fmt::Debug for Miner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Miner")
+            .field("version", &self.version)
+            .field("requested_bits", &self.requested_bits)
+            .field("resource", &self.resource)
+            .field("extension", &self.extension)
+            .field("algorithm", &self.algorithm)
+            .field("format", &self.format)
+            .finish_non_exhaustive()
+    }
+}
+
+// Counters `start..end` handed to one worker when minting is split across machines
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WorkRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+// Counters a worker of `try_mint_parallel` claims from the shared budget at once
+const PARALLEL_CHUNK: usize = 4096;
+
+// Takes up to a chunk from the budget, `None` once it is spent
+fn claim_chunk(budget: &AtomicUsize) -> Option<usize> {
+    budget
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |left| {
+            (left > 0).then(|| left - left.min(PARALLEL_CHUNK))
+        })
+        .ok()
+        .map(|left| left.min(PARALLEL_CHUNK))
+}
+
+impl Stamp {
+    /// Mints on `threads` threads trying at most `max_iterations` counters in
+    /// total, the first solution wins. Worker `i` tries every `threads`th counter
+    /// from the start counter plus `i`, so no counter is tried twice.
+    ///
+    /// Workers claim counters from the shared budget in chunks, so the cap holds
+    /// across threads. If the budget runs out the error holds the unminted stamp
+    /// with its start counter, as no single worker made the last attempt.
+    #[must_use = "mint consumes the stamp, dropping the result discards the work"]
+    // returning the unminted stamp by value lets the caller resume or re-issue it
+    #[allow(clippy::result_large_err)]
+    pub fn try_mint_parallel(self, threads: usize, max_iterations: usize) -> Result<Self, Stamp> {
+        let threads = threads.max(1);
+        let counter_step = self.counter_step;
+        let budget = AtomicUsize::new(max_iterations);
+        let solved = AtomicBool::new(false);
+
+        let winner = thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads)
+                .map(|i| {
+                    let mut stamp = self
+                        .clone()
+                        .with_counter(self.counter.wrapping_add(i.wrapping_mul(counter_step)))
+                        .with_counter_step(counter_step.wrapping_mul(threads));
+                    let (budget, solved) = (&budget, &solved);
+
+                    scope.spawn(move || loop {
+                        let claimed = claim_chunk(budget)?;
+
+                        match stamp.mint_with(Some(claimed), Some(solved)) {
+                            // only the first worker to solve returns its stamp
+                            MintOutcome::Solved(minted) => {
+                                return (!solved.swap(true, Ordering::Relaxed))
+                                    .then(|| minted.with_counter_step(counter_step));
+                            }
+                            MintOutcome::Cancelled(_) => return None,
+                            MintOutcome::Exhausted(unsolved) => stamp = unsolved,
+                        }
+                    })
+                })
+                .collect();
+
+            workers
+                .into_iter()
+                .filter_map(|worker| worker.join().unwrap())
+                .next()
+        });
+
+        winner.ok_or(self)
+    }
+
+    /// Tries every counter of `range` in order and returns the stamp solved with
+    /// the first one that works, `None` if the range holds no solution.
+    ///
+    /// Workers have to mint the same stamp (salt, date, ...) for their solutions
+    /// to be interchangeable. Disjoint ranges split the work, overlapping ranges
+    /// only repeat it and may yield the same solution twice. An exhausted range
+    /// says nothing about others, the coordinator just hands out the next one.
+    #[must_use = "mint consumes the stamp, dropping the result discards the work"]
+    pub fn mint_range(self, range: WorkRange) -> Option<Stamp> {
+        let len = usize::try_from(range.end.checked_sub(range.start)?).ok()?;
+        if len == 0 {
+            return None;
+        }
+
+        let counter_step = self.counter_step;
+        let stamp = self.with_counter(range.start as usize).with_counter_step(1);
+        match stamp.mint_with(Some(len), None) {
+            MintOutcome::Solved(solved) => Some(solved.with_counter_step(counter_step)),
+            _ => None,
+        }
+    }
+
+    /// Endless supply of freshly minted stamps, each with its own salt,
+    /// e.g. `.take(10_000)` to build a load testing corpus.
+    pub fn mint_stream(
+        version: FormatVersion,
+        requested_bits: u8,
+        resource: String,
+    ) -> impl Iterator<Item = Stamp> {
+        let miner = Miner::new(version, requested_bits, resource);
+        std::iter::repeat_with(move || miner.mint_next())
+    }
+}
+
+// Mints one stamp while reporting its progress, so a mint of very high
+// difficulty can survive a restart.
+//
+// The sink receives the next untried counter every `interval` attempts. To
+// resume, persist the unminted stamp (e.g. its string form) next to the counter
+// and pass both to `resume`. With the same salt and date the search continues
+// where it left off and reaches the same solution as an uninterrupted run.
+pub struct CheckpointingMiner<F: FnMut(u64)> {
+    stamp: Stamp,
+    interval: usize,
+    sink: F,
+}
+
+impl<F: FnMut(u64)> CheckpointingMiner<F> {
+    pub fn new(stamp: Stamp, interval: usize, sink: F) -> Self {
+        CheckpointingMiner {
+            stamp,
+            interval: interval.max(1),
+            sink,
+        }
+    }
+
+    /// Continues minting `stamp` from a counter saved by an earlier run.
+    pub fn resume(stamp: Stamp, counter: u64, interval: usize, sink: F) -> Self {
+        CheckpointingMiner::new(stamp.with_counter(counter as usize), interval, sink)
+    }
+
+    pub fn mint(self) -> Stamp {
+        let CheckpointingMiner {
+            mut stamp,
+            interval,
+            mut sink,
+        } = self;
+
+        loop {
+            match stamp.mint_with(Some(interval), None) {
+                MintOutcome::Solved(solved) => return solved,
+                MintOutcome::Cancelled(unsolved) | MintOutcome::Exhausted(unsolved) => {
+                    sink(unsolved.counter() as u64);
+                    stamp = unsolved;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn miner_keeps_the_algorithm_of_its_extension() {
+        let miner = Miner::new(FormatVersion::V1, 8, "r".to_string());
+        assert_eq!(miner.mint_next().algorithm(), Algorithm::Sha1);
+
+        let stamp = miner
+            .clone()
+            .with_extension("alg=sha256".to_string())
+            .mint_next();
+        assert_eq!(stamp.algorithm(), Algorithm::Sha256);
+        assert!(stamp.check_no_expiry("r", 8).is_ok());
+
+        let stamp = miner.with_algorithm(Algorithm::Sha256).mint_next();
+        assert_eq!(stamp.algorithm(), Algorithm::Sha256);
+        assert!(stamp.check_no_expiry("r", 8).is_ok());
+    }
+
+    #[test]
+    fn miner_draws_a_fresh_salt_for_every_stamp() {
+        let format = StampFormat::with_separator('|').unwrap();
+        let miner = Miner::new(FormatVersion::V1, 8, "r".to_string()).with_format(format);
+        let (first, second) = (miner.mint_next(), miner.mint_next());
+
+        assert_ne!(first.salt, second.salt);
+        for stamp in [first, second] {
+            assert_eq!(stamp.format, format);
+            assert!(!stamp.is_dirty());
+            assert!(stamp.check_no_expiry("r", 8).is_ok());
+        }
+    }
+
+    #[test]
+    fn resumed_mint_reaches_the_uninterrupted_solution() {
+        let unminted = Stamp::new(FormatVersion::V1, 14, "r".to_string(), None);
+        let persisted = unminted.to_string();
+
+        let mut checkpoints = Vec::new();
+        let uninterrupted =
+            CheckpointingMiner::new(unminted, 16, |counter| checkpoints.push(counter)).mint();
+
+        // as if the process died after the middle checkpoint
+        let checkpoint = checkpoints.get(checkpoints.len() / 2).copied().unwrap_or(0);
+        let restored = Stamp::try_from(persisted).unwrap();
+        let resumed = CheckpointingMiner::resume(restored, checkpoint, 16, |_| {}).mint();
+
+        assert_eq!(resumed.counter(), uninterrupted.counter());
+        assert_eq!(resumed.to_string(), uninterrupted.to_string());
+        assert!(resumed.check_no_expiry("r", 14).is_ok());
+    }
+
+    #[test]
+    fn parallel_claims_never_exceed_the_budget() {
+        for max_iterations in [0, 1, PARALLEL_CHUNK - 1, PARALLEL_CHUNK + 1, 100_000] {
+            let budget = AtomicUsize::new(max_iterations);
+            let claimed = AtomicUsize::new(0);
+
+            thread::scope(|scope| {
+                for _ in 0..8 {
+                    scope.spawn(|| {
+                        while let Some(chunk) = claim_chunk(&budget) {
+                            claimed.fetch_add(chunk, Ordering::Relaxed);
+                        }
+                    });
+                }
+            });
+
+            assert_eq!(claimed.load(Ordering::Relaxed), max_iterations);
+        }
+    }
+
+    #[test]
+    fn parallel_mint_stops_at_the_budget() {
+        // no SHA-1 digest has 160 leading zero bits
+        let unminted = Stamp::new(FormatVersion::V1, 160, "r".to_string(), None);
+        match unminted
+            .clone()
+            .try_mint_parallel(8, 3 * PARALLEL_CHUNK + 1)
+        {
+            Err(stamp) => assert_eq!(stamp.counter(), unminted.counter()),
+            Ok(_) => panic!("minted an impossible stamp"),
+        }
+    }
+}
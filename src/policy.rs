@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use chrono::Duration;
+
+use crate::{HashcashError, Stamp};
+
+// Difficulty and expiry a server demands for a resource
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Policy {
+    pub bits: u8,
+    pub expiry: Duration,
+}
+
+// Maps resources to policies, either exactly or through `*`/`?` glob patterns
+#[derive(Clone, Debug, Default)]
+pub struct PolicyTable {
+    exact: HashMap<String, Policy>,
+    // tried in insertion order after the exact matches
+    patterns: Vec<(String, Policy)>,
+    default: Option<Policy>,
+}
+
+impl PolicyTable {
+    pub fn new() -> Self {
+        PolicyTable::default()
+    }
+
+    /// Adds a policy for a resource, patterns containing `*` or `?` are matched as globs.
+    pub fn insert(&mut self, pattern: &str, policy: Policy) {
+        if pattern.contains(['*', '?']) {
+            self.patterns.push((pattern.to_string(), policy));
+        } else {
+            self.exact.insert(pattern.to_string(), policy);
+        }
+    }
+
+    /// Policy for resources no entry matches, without one they are rejected.
+    pub fn set_default(&mut self, policy: Policy) {
+        self.default = Some(policy);
+    }
+
+    pub fn lookup(&self, resource: &str) -> Option<&Policy> {
+        self.exact
+            .get(resource)
+            .or_else(|| {
+                self.patterns
+                    .iter()
+                    .find(|(pattern, _)| glob_matches(pattern, resource))
+                    .map(|(_, policy)| policy)
+            })
+            .or(self.default.as_ref())
+    }
+}
+
+impl Stamp {
+    /// Checks the stamp against the policy for its own resource. The bits come
+    /// from the table, not from the stamp's claimed `requested_bits`.
+    pub fn check_with_table(&self, table: &PolicyTable) -> Result<&Self, HashcashError> {
+        if self.dirty {
+            return Err(HashcashError::StaleProof);
+        }
+
+        let policy = match table.lookup(&self.resource) {
+            Some(v) => v,
+            None => return Err(HashcashError::NoPolicy),
+        };
+
+        if self.is_expired(&policy.expiry) {
+            return Err(HashcashError::Expired);
+        }
+
+        self.validate_bits(policy.bits)?;
+
+        Ok(self)
+    }
+
+    /// Like `check_bits` but accepts any resource matching a glob such as `*@example.com`.
+    pub fn check_resource_pattern(
+        &self,
+        pattern: &str,
+        expiry_duration: &Duration,
+        required_bits: u8,
+    ) -> Result<&Self, HashcashError> {
+        if !glob_matches(pattern, &self.resource) {
+            return Err(HashcashError::ResourceMismatch);
+        }
+
+        if self.is_expired(expiry_duration) {
+            return Err(HashcashError::Expired);
+        }
+
+        self.validate_bits(required_bits)?;
+
+        Ok(self)
+    }
+}
+
+// `*` matches any run of characters and `?` exactly one
+pub(crate) fn glob_matches(pattern: &str, value: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let value: Vec<char> = value.chars().collect();
+
+    let (mut p, mut v) = (0, 0);
+    // position of the last `*` and the value index it is currently matched up to
+    let mut backtrack = None;
+
+    while v < value.len() {
+        // a `*` is a wildcard even where the value has a literal one
+        if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, v));
+            p += 1;
+        } else if p < pattern.len() && (pattern[p] == '?' || pattern[p] == value[v]) {
+            p += 1;
+            v += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            v = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FormatVersion, Target};
+
+    #[test]
+    fn table_bits_are_counted_the_way_the_target_does() {
+        let mut table = PolicyTable::new();
+        table.set_default(Policy {
+            bits: 12,
+            expiry: Duration::hours(1),
+        });
+
+        for target in [Target::BitSuffix, Target::NibblePrefix, Target::BytePrefix] {
+            let stamp = Stamp::new(FormatVersion::V1, 16, "r".to_string(), None)
+                .with_target(target)
+                .mint();
+            assert!(stamp.check_with_table(&table).is_ok());
+        }
+    }
+
+    #[test]
+    fn star_matches_a_literal_star_in_the_value() {
+        assert!(glob_matches("*a", "*ba"));
+        assert!(glob_matches("*", "*"));
+        assert!(glob_matches("a*c", "a*b*c"));
+        assert!(!glob_matches("*a", "*b"));
+    }
+}
@@ -0,0 +1,273 @@
+use std::io::{self, Write};
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use chrono::{Duration, NaiveDate};
+
+use crate::{to_hex, Algorithm, FormatVersion, HashcashError, Stamp};
+
+// Joins the parts of a compound resource such as `ip;email`
+static COMPOUND_SEPARATOR: char = ';';
+
+// Helpers for building resource strings
+pub struct Resource;
+
+// What a resource looks like, detected by simple patterns only
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResourceKind {
+    Email,
+    IpV4,
+    IpV6,
+    Url,
+    Other,
+}
+
+impl Resource {
+    /// Binds several values into one resource, e.g. sender IP and recipient mailbox.
+    /// Parts containing the compound separator or `field_separator`, the one of the
+    /// stamp's `StampFormat`, are rejected so the joined resource can't be split
+    /// ambiguously.
+    pub fn compound(parts: &[&str], field_separator: char) -> Result<String, HashcashError> {
+        if parts
+            .iter()
+            .any(|part| part.contains(COMPOUND_SEPARATOR) || part.contains(field_separator))
+        {
+            return Err(HashcashError::InvalidResource);
+        }
+
+        Ok(parts.join(&COMPOUND_SEPARATOR.to_string()))
+    }
+
+    /// Resource binding a stamp to arbitrary content, the hex SHA-256 digest of `data`.
+    pub fn data(data: &[u8]) -> String {
+        to_hex(&Algorithm::Sha256.digest(data))
+    }
+
+    /// Resource valid for one UTC day only, `base;YYYYMMDD`, so yesterday's
+    /// stamps can't be reused today. See `Stamp::check_dated`.
+    pub fn dated(base: &str, date: NaiveDate) -> String {
+        format!("{}{}{}", base, COMPOUND_SEPARATOR, date.format("%Y%m%d"))
+    }
+
+    /// Resource committing to `resource` without revealing it, the hex SHA-256
+    /// digest of `salt || resource`. The salt keeps guessable resources such as
+    /// email addresses from being found by hashing candidates.
+    pub fn commit(salt: &[u8], resource: &str) -> String {
+        let mut data = salt.to_vec();
+        data.extend_from_slice(resource.as_bytes());
+        Resource::data(&data)
+    }
+
+    /// Guesses the kind of a resource, e.g. to pick a policy per kind.
+    /// Only looks at the shape, an `Email` is not necessarily a deliverable address.
+    pub fn classify(resource: &str) -> ResourceKind {
+        if resource.parse::<Ipv4Addr>().is_ok() {
+            return ResourceKind::IpV4;
+        }
+
+        if resource.parse::<Ipv6Addr>().is_ok() {
+            return ResourceKind::IpV6;
+        }
+
+        if let Some((scheme, rest)) = resource.split_once("://") {
+            if !scheme.is_empty() && !rest.is_empty() {
+                return ResourceKind::Url;
+            }
+        }
+
+        match resource.split_once('@') {
+            Some((local, domain))
+                if !local.is_empty() && domain.contains('.') && !domain.contains('@') =>
+            {
+                ResourceKind::Email
+            }
+            _ => ResourceKind::Other,
+        }
+    }
+}
+
+impl Stamp {
+    /// Mints a V1 stamp proving work over `data`, the resource is the data's SHA-256 hex digest.
+    pub fn for_data(data: &[u8], requested_bits: u8) -> Self {
+        Stamp::new(
+            FormatVersion::V1,
+            requested_bits,
+            Resource::data(data),
+            None,
+        )
+        .mint()
+    }
+
+    pub fn classify_resource(&self) -> ResourceKind {
+        Resource::classify(&self.resource)
+    }
+
+    /// Like `check_bits` with the resource recomputed from `data`.
+    pub fn check_data(
+        &self,
+        data: &[u8],
+        expiry_duration: &Duration,
+        required_bits: u8,
+    ) -> Result<&Self, HashcashError> {
+        self.check_bits(&Resource::data(data), expiry_duration, required_bits)
+    }
+
+    /// Like `check_bits` with today's `Resource::dated` resource, and yesterday's as
+    /// well with `allow_yesterday` so stamps minted just before midnight pass.
+    pub fn check_dated(
+        &self,
+        base: &str,
+        expiry_duration: &Duration,
+        required_bits: u8,
+        allow_yesterday: bool,
+    ) -> Result<&Self, HashcashError> {
+        let today = chrono::offset::Utc::now().date_naive();
+        let mut resources = vec![Resource::dated(base, today)];
+        if let Some(yesterday) = today.pred_opt().filter(|_| allow_yesterday) {
+            resources.push(Resource::dated(base, yesterday));
+        }
+
+        let resources: Vec<&str> = resources.iter().map(String::as_str).collect();
+        self.check_rotating(&resources, expiry_duration, required_bits)
+    }
+
+    /// Like `check_bits` with the resource recomputed by `Resource::commit`.
+    pub fn check_commitment(
+        &self,
+        salt: &[u8],
+        resource: &str,
+        expiry_duration: &Duration,
+        required_bits: u8,
+    ) -> Result<&Self, HashcashError> {
+        self.check_bits(
+            &Resource::commit(salt, resource),
+            expiry_duration,
+            required_bits,
+        )
+    }
+
+    /// Like `check_bits` with the expected resource built by `Resource::compound`.
+    pub fn check_compound(
+        &self,
+        parts: &[&str],
+        expiry_duration: &Duration,
+        required_bits: u8,
+    ) -> Result<&Self, HashcashError> {
+        let resource = Resource::compound(parts, self.format.separator)?;
+        self.check_bits(&resource, expiry_duration, required_bits)
+    }
+}
+
+// Encodes everything but unreserved URL characters (RFC 3986)
+pub(crate) fn percent_encode(value: &str) -> String {
+    let mut encoded = Vec::with_capacity(value.len());
+    percent_encode_into(value, &mut encoded).unwrap();
+    String::from_utf8(encoded).unwrap()
+}
+
+// Writes the percent-encoded value, which is always ASCII
+pub(crate) fn percent_encode_into(value: &str, out: &mut impl Write) -> io::Result<()> {
+    const HEX: &[u8; 16] = b"0123456789ABCDEF";
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                out.write_all(&[byte])?
+            }
+            _ => out.write_all(&[
+                b'%',
+                HEX[usize::from(byte >> 4)],
+                HEX[usize::from(byte & 0xf)],
+            ])?,
+        }
+    }
+    Ok(())
+}
+
+// Only accepts the form `percent_encode` writes, so the decoded resource hashes
+// to the bytes on the wire: e.g. `%2f`, `%41` or a bare `@` are rejected
+pub(crate) fn percent_decode(value: &str) -> Result<String, HashcashError> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let byte = match bytes.get(i + 1..i + 3) {
+                Some(hex) if hex.iter().all(u8::is_ascii_hexdigit) => {
+                    u8::from_str_radix(std::str::from_utf8(hex).unwrap(), 16).unwrap()
+                }
+                _ => return Err(HashcashError::InvalidResource),
+            };
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    match String::from_utf8(decoded) {
+        Ok(v) if percent_encode(&v) == value => Ok(v),
+        _ => Err(HashcashError::InvalidResource),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StampFormat;
+
+    #[test]
+    fn compound_parts_are_checked_against_the_formats_separator() {
+        assert_eq!(
+            Resource::compound(&["a", "b:c"], ':').err(),
+            Some(HashcashError::InvalidResource)
+        );
+        assert_eq!(
+            Resource::compound(&["a", "b|c"], '|').err(),
+            Some(HashcashError::InvalidResource)
+        );
+
+        let format = StampFormat::with_separator('|').unwrap();
+        let parts = ["10.0.0.1", "mailto:foo@example.org"];
+        let stamp = Stamp::new(
+            FormatVersion::V1,
+            8,
+            Resource::compound(&parts, '|').unwrap(),
+            None,
+        )
+        .with_format(format)
+        .mint();
+        assert!(stamp.check_compound(&parts, &Duration::hours(1), 8).is_ok());
+    }
+
+    #[test]
+    fn commitments_are_deterministic() {
+        let commitment = Resource::commit(b"salt", "foo@example.org");
+        assert_eq!(commitment, Resource::commit(b"salt", "foo@example.org"));
+        assert_eq!(
+            commitment,
+            "dbb45a5f04053a05f480fd9b5a4d4e1abc485ace32330eb0e9bb59c719e411c2"
+        );
+
+        assert_ne!(commitment, Resource::commit(b"pepper", "foo@example.org"));
+        assert_ne!(commitment, Resource::commit(b"salt", "bar@example.org"));
+    }
+
+    #[test]
+    fn rejects_non_canonical_percent_escapes() {
+        let resource = "foo bar/@example.org";
+        assert_eq!(percent_encode(resource), "foo%20bar%2F%40example.org");
+        assert_eq!(
+            percent_decode(&percent_encode(resource)).as_deref(),
+            Ok(resource)
+        );
+
+        for value in ["%+F", "%2f", "%41", "a@b", "%2", "%", "%zz", "%C3"] {
+            assert_eq!(
+                percent_decode(value),
+                Err(HashcashError::InvalidResource),
+                "{}",
+                value
+            );
+        }
+    }
+}
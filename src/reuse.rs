@@ -0,0 +1,29 @@
+use std::collections::HashMap;
+
+use crate::Stamp;
+
+// Flags salts that show up with more than one resource, an abuse signal
+// orthogonal to replay detection. Entries are never evicted.
+#[derive(Clone, Debug, Default)]
+pub struct SaltReuseDetector {
+    // first resource each salt was seen with
+    resources: HashMap<Vec<u8>, String>,
+}
+
+impl SaltReuseDetector {
+    pub fn new() -> Self {
+        SaltReuseDetector::default()
+    }
+
+    /// Records the stamp's salt, returns true if it was seen before with another resource.
+    pub fn observe(&mut self, stamp: &Stamp) -> bool {
+        match self.resources.get(&stamp.salt) {
+            Some(resource) => *resource != stamp.resource,
+            None => {
+                self.resources
+                    .insert(stamp.salt.clone(), stamp.resource.clone());
+                false
+            }
+        }
+    }
+}
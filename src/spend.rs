@@ -0,0 +1,399 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+#[cfg(feature = "tokio")]
+use std::sync::{Arc, Weak};
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::{to_hex, Algorithm, HashcashError, Stamp, StampFields, StampFormat};
+
+// Remembers redeemed stamps to protect against double spending
+pub trait SpendStore {
+    /// Records `key`, returns false if it was recorded before. Has to be atomic:
+    /// of concurrent inserts of the same key exactly one may return true.
+    fn try_insert(&self, key: &str) -> bool;
+}
+
+// Async counterpart of `SpendStore` for stores living in e.g. Redis (`SETNX` + `EXPIRE`)
+#[cfg(feature = "async")]
+pub trait AsyncSpendStore {
+    /// Records `key`, resolves to false if it was recorded before.
+    fn try_insert(&self, key: &str) -> impl std::future::Future<Output = bool> + Send;
+}
+
+// In-memory spend store, entries are never evicted
+#[derive(Default)]
+pub struct MemorySpendStore {
+    keys: Mutex<HashSet<String>>,
+}
+
+impl MemorySpendStore {
+    pub fn new() -> Self {
+        MemorySpendStore::default()
+    }
+}
+
+impl SpendStore for MemorySpendStore {
+    fn try_insert(&self, key: &str) -> bool {
+        self.keys.lock().unwrap().insert(key.to_string())
+    }
+}
+
+// In-memory spend store split into independently locked shards, so concurrent
+// redemptions only contend when their keys land in the same shard
+pub struct ShardedSpendStore {
+    shards: Vec<Mutex<HashSet<String>>>,
+}
+
+impl ShardedSpendStore {
+    pub fn new(shards: usize) -> Self {
+        ShardedSpendStore {
+            shards: (0..shards.max(1)).map(|_| Mutex::default()).collect(),
+        }
+    }
+
+    fn shard(&self, key: &str) -> &Mutex<HashSet<String>> {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        &self.shards[hasher.finish() as usize % self.shards.len()]
+    }
+}
+
+impl SpendStore for ShardedSpendStore {
+    fn try_insert(&self, key: &str) -> bool {
+        self.shard(key).lock().unwrap().insert(key.to_string())
+    }
+}
+
+// In-memory spend store holding at most `capacity` keys, the oldest key is
+// evicted to make room. Replays within the last `capacity` redemptions are
+// caught, older stamps may be redeemed again, so pair it with an expiry that
+// lets stamps expire before they drop out of the ring.
+pub struct RingSpendStore {
+    capacity: usize,
+    // the keys for lookups and their insertion order for eviction
+    keys: Mutex<(HashSet<String>, VecDeque<String>)>,
+}
+
+impl RingSpendStore {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        RingSpendStore {
+            capacity,
+            keys: Mutex::new((
+                HashSet::with_capacity(capacity),
+                VecDeque::with_capacity(capacity),
+            )),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+impl SpendStore for RingSpendStore {
+    fn try_insert(&self, key: &str) -> bool {
+        let mut keys = self.keys.lock().unwrap();
+        let (set, order) = &mut *keys;
+
+        if set.contains(key) {
+            return false;
+        }
+
+        if order.len() == self.capacity {
+            if let Some(oldest) = order.pop_front() {
+                set.remove(&oldest);
+            }
+        }
+
+        set.insert(key.to_string());
+        order.push_back(key.to_string());
+        true
+    }
+}
+
+// In-memory spend store forgetting keys after `ttl`, which should be at least
+// the expiry stamps are checked with so expired stamps can't be replayed.
+// Inserts prune the entries that expired first, `purge_expired` sweeps all of them.
+pub struct TtlSpendStore {
+    ttl: Duration,
+    state: Mutex<TtlState>,
+}
+
+#[derive(Default)]
+struct TtlState {
+    // key and the instant it may be forgotten
+    keys: HashMap<String, DateTime<Utc>>,
+    // the same entries in insertion order, which with a fixed ttl is expiry
+    // order unless the clock went backwards
+    expiries: VecDeque<(DateTime<Utc>, String)>,
+}
+
+impl TtlState {
+    // Forgets the expired entries at the front of `expiries`
+    fn prune_front(&mut self, now: DateTime<Utc>) {
+        while let Some((forget_at, _)) = self.expiries.front() {
+            if *forget_at > now {
+                break;
+            }
+
+            let (forget_at, key) = self.expiries.pop_front().unwrap();
+            if self.keys.get(&key) == Some(&forget_at) {
+                self.keys.remove(&key);
+            }
+        }
+    }
+}
+
+impl TtlSpendStore {
+    pub fn new(ttl: Duration) -> Self {
+        TtlSpendStore {
+            ttl,
+            state: Mutex::default(),
+        }
+    }
+
+    /// Drops every entry whose ttl has passed at `now`.
+    pub fn purge_expired(&self, now: DateTime<Utc>) {
+        let mut state = self.state.lock().unwrap();
+        state.keys.retain(|_, forget_at| *forget_at > now);
+        state.expiries.retain(|(forget_at, _)| *forget_at > now);
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl SpendStore for TtlSpendStore {
+    fn try_insert(&self, key: &str) -> bool {
+        let now = chrono::offset::Utc::now();
+        let mut state = self.state.lock().unwrap();
+        state.prune_front(now);
+
+        if let Some(forget_at) = state.keys.get(key) {
+            // entries behind a later one after a clock change aren't pruned yet
+            if *forget_at > now {
+                return false;
+            }
+        }
+
+        // huge ttls saturate instead of overflowing the date
+        let forget_at = now
+            .checked_add_signed(self.ttl)
+            .unwrap_or(DateTime::<Utc>::MAX_UTC);
+        state.keys.insert(key.to_string(), forget_at);
+        state.expiries.push_back((forget_at, key.to_string()));
+        true
+    }
+}
+
+/// Purges `store` every `interval` on the current tokio runtime, so quiet
+/// stores don't keep expired entries around. The task ends once the store is dropped.
+#[cfg(feature = "tokio")]
+pub fn spawn_purger(
+    store: &Arc<TtlSpendStore>,
+    interval: std::time::Duration,
+) -> tokio::task::JoinHandle<()> {
+    let store: Weak<TtlSpendStore> = Arc::downgrade(store);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match store.upgrade() {
+                Some(store) => store.purge_expired(chrono::offset::Utc::now()),
+                None => break,
+            }
+        }
+    })
+}
+
+impl Stamp {
+    /// Key a stamp is recorded under in a spend store, its wire form as the proof
+    /// covers it. An extension that isn't hashed is left blank, editing it doesn't
+    /// make the same proof a new stamp.
+    pub fn dedup_key(&self) -> String {
+        self.hashed_fields()
+    }
+
+    /// Fixed-width alternative to `dedup_key` for stores that bound key sizes, the
+    /// hex SHA-256 digest of the same fields. Always SHA-256 whatever the stamp
+    /// is minted with: the proof-of-work hash only has to be hard to get zero bits
+    /// from, a key must not collide and SHA-1 collisions can be crafted.
+    pub fn collision_resistant_key(&self) -> String {
+        to_hex(&Algorithm::Sha256.digest(self.hashed_fields().as_bytes()))
+    }
+
+    /// Line for the spend database of the reference hashcash tool, `<date> <resource> <stamp>`.
+    pub fn to_hashcash_db_line(&self) -> String {
+        let fields = StampFields::from_stamp(self);
+        format!("{} {} {}", fields.date, self.resource, self)
+    }
+
+    /// Reads a line written by `to_hashcash_db_line`, the date and resource
+    /// columns have to agree with the stamp. The stamp is parsed with `format`,
+    /// `StampFormat::reference()` for a `hashcash.db` kept by the C tool.
+    pub fn from_hashcash_db_line(line: &str, format: StampFormat) -> Result<Self, HashcashError> {
+        let line = line.trim_end();
+        let (date, rest) = line.split_once(' ').ok_or(HashcashError::MissingFields)?;
+        let (resource, stamp) = rest.rsplit_once(' ').ok_or(HashcashError::MissingFields)?;
+
+        let stamp = Stamp::parse_with_format(stamp, format)?;
+        if !stamp.resource_matches(resource) {
+            return Err(HashcashError::ResourceMismatch);
+        }
+
+        if StampFields::from_stamp(&stamp).date != date {
+            return Err(HashcashError::InvalidDate);
+        }
+
+        Ok(stamp)
+    }
+
+    /// Checks the stamp and records it as spent, a second redemption fails with `AlreadySpent`.
+    ///
+    /// Safe under concurrent redemption of the same stamp: the check touches no
+    /// shared state, and the store's atomic `try_insert` lets only one of the
+    /// redemptions that passed it succeed.
+    pub fn redeem(
+        &self,
+        resource: &str,
+        expiry_duration: &Duration,
+        required_bits: u8,
+        store: &impl SpendStore,
+    ) -> Result<(), HashcashError> {
+        self.check_bits(resource, expiry_duration, required_bits)?;
+
+        if !store.try_insert(&self.dedup_key()) {
+            return Err(HashcashError::AlreadySpent);
+        }
+
+        Ok(())
+    }
+
+    /// `redeem` under the name servers look for: the check and the store's atomic
+    /// `try_insert` redeem a stamp at most once however many threads present it.
+    pub fn verify_and_redeem(
+        &self,
+        resource: &str,
+        expiry_duration: &Duration,
+        required_bits: u8,
+        store: &impl SpendStore,
+    ) -> Result<(), HashcashError> {
+        self.redeem(resource, expiry_duration, required_bits, store)
+    }
+
+    #[cfg(feature = "async")]
+    pub async fn redeem_async(
+        &self,
+        resource: &str,
+        expiry_duration: &Duration,
+        required_bits: u8,
+        store: &impl AsyncSpendStore,
+    ) -> Result<(), HashcashError> {
+        self.check_bits(resource, expiry_duration, required_bits)?;
+
+        if !store.try_insert(&self.dedup_key()).await {
+            return Err(HashcashError::AlreadySpent);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FormatVersion, DEFAULT_EXPIRY};
+
+    #[test]
+    fn an_unhashed_extension_cant_replay_a_proof() {
+        let format = StampFormat {
+            hash_extension: false,
+            ..StampFormat::default()
+        };
+        let minted = Stamp::new(FormatVersion::V1, 8, "r".to_string(), None)
+            .with_format(format)
+            .mint();
+        let wire = minted.to_string().replacen(":r::", ":r:replay=1:", 1);
+        let edited = Stamp::parse_with_format(&wire, format).unwrap();
+
+        let store = MemorySpendStore::new();
+        assert!(minted.redeem("r", &DEFAULT_EXPIRY, 8, &store).is_ok());
+        assert_eq!(
+            edited.redeem("r", &DEFAULT_EXPIRY, 8, &store).err(),
+            Some(HashcashError::AlreadySpent)
+        );
+        assert_eq!(
+            edited.collision_resistant_key(),
+            minted.collision_resistant_key()
+        );
+        assert!(edited == minted);
+    }
+
+    #[test]
+    fn ttl_store_prunes_expired_entries_on_insert() {
+        let store = TtlSpendStore::new(Duration::zero());
+        assert!(store.try_insert("a"));
+        assert!(store.try_insert("b"));
+        assert_eq!(store.len(), 1);
+
+        assert!(store.try_insert("a"));
+        assert_eq!(store.state.lock().unwrap().expiries.len(), 1);
+    }
+
+    #[test]
+    fn ttl_store_rejects_keys_until_purged() {
+        let store = TtlSpendStore::new(Duration::days(1));
+        assert!(store.try_insert("a"));
+        assert!(!store.try_insert("a"));
+
+        store.purge_expired(Utc::now());
+        assert_eq!(store.len(), 1);
+
+        store.purge_expired(Utc::now() + Duration::days(2));
+        assert!(store.is_empty());
+        assert!(store.state.lock().unwrap().expiries.is_empty());
+        assert!(store.try_insert("a"));
+    }
+
+    #[test]
+    fn ttl_store_saturates_huge_ttls() {
+        let store = TtlSpendStore::new(Duration::max_value());
+        assert!(store.try_insert("a"));
+        assert!(!store.try_insert("a"));
+        assert_eq!(
+            store.state.lock().unwrap().keys["a"],
+            DateTime::<Utc>::MAX_UTC
+        );
+
+        store.purge_expired(Utc::now());
+        assert_eq!(store.len(), 1);
+    }
+
+    #[test]
+    fn ring_store_evicts_the_oldest_key() {
+        assert_eq!(RingSpendStore::new(0).capacity(), 1);
+
+        let store = RingSpendStore::new(3);
+        assert_eq!(store.capacity(), 3);
+        for key in ["a", "b", "c"] {
+            assert!(store.try_insert(key));
+        }
+
+        // "d" pushes out "a", which then pushes out "b"
+        assert!(store.try_insert("d"));
+        assert!(store.try_insert("a"));
+        assert!(!store.try_insert("c"));
+        assert!(!store.try_insert("d"));
+        assert!(store.try_insert("b"));
+        assert_eq!(store.keys.lock().unwrap().1, ["d", "a", "b"]);
+    }
+}
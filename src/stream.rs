@@ -0,0 +1,51 @@
+use std::io::{self, BufRead, Write};
+
+use chrono::Duration;
+
+use crate::{HashcashError, Stamp};
+
+impl Stamp {
+    /// Writes the stamp's wire form field by field straight into `w`, without
+    /// allocating. No newline is written, `check_reader` expects one per stamp.
+    pub fn write_to(&self, w: &mut impl Write) -> io::Result<()> {
+        self.write_fields(w, true)
+    }
+
+    /// Serializes a pool of stamps one per line, each line ending in a newline.
+    pub fn serialize_batch(stamps: &[Stamp]) -> String {
+        stamps.iter().map(|stamp| format!("{}\n", stamp)).collect()
+    }
+
+    /// Parses a blob written by `serialize_batch`, one result per line so a
+    /// malformed line doesn't fail the whole batch.
+    pub fn parse_batch(batch: &str) -> Vec<Result<Stamp, HashcashError>> {
+        batch
+            .lines()
+            .map(|line| Stamp::try_from(line.to_string()))
+            .collect()
+    }
+
+    /// Lazily checks a reader holding one stamp per line, yielding the 1-based
+    /// line number with its result. Malformed lines don't end the stream.
+    pub fn check_reader(
+        reader: impl BufRead,
+        resource: &str,
+        expiry_duration: &Duration,
+        required_bits: u8,
+    ) -> impl Iterator<Item = (usize, Result<(), HashcashError>)> {
+        let resource = resource.to_string();
+        let expiry_duration = *expiry_duration;
+
+        reader.lines().enumerate().map(move |(index, line)| {
+            let result = match line {
+                Ok(line) => Stamp::try_from(line).and_then(|stamp| {
+                    stamp
+                        .check_bits(&resource, &expiry_duration, required_bits)
+                        .map(|_| ())
+                }),
+                Err(e) => Err(HashcashError::Io(e.kind())),
+            };
+            (index + 1, result)
+        })
+    }
+}
@@ -0,0 +1,194 @@
+use chrono::Duration;
+
+use crate::{HashcashError, PolicyTable, Stamp};
+
+// One verification step, steps can be stored as `Box<dyn StampVerifier>` and combined
+pub trait StampVerifier {
+    fn verify(&self, stamp: &Stamp) -> Result<(), HashcashError>;
+}
+
+impl<V: StampVerifier + ?Sized> StampVerifier for Box<V> {
+    fn verify(&self, stamp: &Stamp) -> Result<(), HashcashError> {
+        (**self).verify(stamp)
+    }
+}
+
+// The stamp meets the difficulty it claims, see `Stamp::validate_self`
+pub struct ClaimedBits;
+
+impl StampVerifier for ClaimedBits {
+    fn verify(&self, stamp: &Stamp) -> Result<(), HashcashError> {
+        stamp.validate_self()
+    }
+}
+
+// The stamp meets this many bits under its target, whatever it claims, see `Stamp::check_bits`
+pub struct MinBits(pub u8);
+
+impl StampVerifier for MinBits {
+    fn verify(&self, stamp: &Stamp) -> Result<(), HashcashError> {
+        stamp.validate_bits(self.0)
+    }
+}
+
+// The stamp is for the resource and not expired
+pub struct ResourceAndExpiry {
+    pub resource: String,
+    pub expiry: Duration,
+}
+
+impl StampVerifier for ResourceAndExpiry {
+    fn verify(&self, stamp: &Stamp) -> Result<(), HashcashError> {
+        if !stamp.resource_matches(&self.resource) {
+            return Err(HashcashError::ResourceMismatch);
+        }
+
+        if stamp.is_expired(&self.expiry) {
+            return Err(HashcashError::Expired);
+        }
+
+        Ok(())
+    }
+}
+
+impl StampVerifier for PolicyTable {
+    fn verify(&self, stamp: &Stamp) -> Result<(), HashcashError> {
+        stamp.check_with_table(self).map(|_| ())
+    }
+}
+
+// Passes if every verifier passes, fails with the first error
+pub struct AllOf(pub Vec<Box<dyn StampVerifier>>);
+
+impl StampVerifier for AllOf {
+    fn verify(&self, stamp: &Stamp) -> Result<(), HashcashError> {
+        self.0
+            .iter()
+            .try_for_each(|verifier| verifier.verify(stamp))
+    }
+}
+
+// Passes if any verifier passes, fails with the first error otherwise
+pub struct AnyOf(pub Vec<Box<dyn StampVerifier>>);
+
+impl StampVerifier for AnyOf {
+    fn verify(&self, stamp: &Stamp) -> Result<(), HashcashError> {
+        let mut first_error = None;
+        for verifier in &self.0 {
+            match verifier.verify(stamp) {
+                Ok(()) => return Ok(()),
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        Err(first_error.unwrap_or(HashcashError::Rejected))
+    }
+}
+
+// Passes if the verifier fails
+pub struct Not(pub Box<dyn StampVerifier>);
+
+impl StampVerifier for Not {
+    fn verify(&self, stamp: &Stamp) -> Result<(), HashcashError> {
+        match self.0.verify(stamp) {
+            Ok(()) => Err(HashcashError::Rejected),
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+pub fn all_of(verifiers: Vec<Box<dyn StampVerifier>>) -> AllOf {
+    AllOf(verifiers)
+}
+
+/// An empty list never passes.
+pub fn any_of(verifiers: Vec<Box<dyn StampVerifier>>) -> AnyOf {
+    AnyOf(verifiers)
+}
+
+pub fn not(verifier: impl StampVerifier + 'static) -> Not {
+    Not(Box::new(verifier))
+}
+
+impl Stamp {
+    pub fn check_with(&self, verifier: &impl StampVerifier) -> Result<&Self, HashcashError> {
+        verifier.verify(self)?;
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FormatVersion, Target};
+
+    // Passes or fails with the given error whatever the stamp
+    struct Fixed(Result<(), HashcashError>);
+
+    impl StampVerifier for Fixed {
+        fn verify(&self, _: &Stamp) -> Result<(), HashcashError> {
+            self.0.clone()
+        }
+    }
+
+    fn pass() -> Box<dyn StampVerifier> {
+        Box::new(Fixed(Ok(())))
+    }
+
+    fn fail(e: HashcashError) -> Box<dyn StampVerifier> {
+        Box::new(Fixed(Err(e)))
+    }
+
+    fn stamp() -> Stamp {
+        Stamp::new(FormatVersion::V1, 8, "r".to_string(), None).mint()
+    }
+
+    #[test]
+    fn all_of_fails_with_the_first_error() {
+        let stamp = stamp();
+        assert!(all_of(vec![]).verify(&stamp).is_ok());
+        assert!(all_of(vec![pass(), pass()]).verify(&stamp).is_ok());
+
+        let verifier = all_of(vec![
+            pass(),
+            fail(HashcashError::Expired),
+            fail(HashcashError::InsufficientBits),
+        ]);
+        assert_eq!(verifier.verify(&stamp), Err(HashcashError::Expired));
+    }
+
+    #[test]
+    fn any_of_passes_with_one_passing_verifier() {
+        let stamp = stamp();
+        assert_eq!(any_of(vec![]).verify(&stamp), Err(HashcashError::Rejected));
+        assert!(any_of(vec![fail(HashcashError::Expired), pass()])
+            .verify(&stamp)
+            .is_ok());
+
+        let verifier = any_of(vec![
+            fail(HashcashError::Expired),
+            fail(HashcashError::InsufficientBits),
+        ]);
+        assert_eq!(verifier.verify(&stamp), Err(HashcashError::Expired));
+    }
+
+    #[test]
+    fn not_inverts_the_verifier() {
+        let stamp = stamp();
+        assert_eq!(
+            not(ClaimedBits).verify(&stamp),
+            Err(HashcashError::Rejected)
+        );
+        assert!(not(MinBits(160)).verify(&stamp).is_ok());
+        assert!(not(fail(HashcashError::Expired)).verify(&stamp).is_ok());
+    }
+
+    #[test]
+    fn min_bits_counts_the_way_the_target_does() {
+        let stamp = Stamp::new(FormatVersion::V1, 16, "r".to_string(), None)
+            .with_target(Target::BitSuffix)
+            .mint();
+        assert!(MinBits(16).verify(&stamp).is_ok());
+    }
+}
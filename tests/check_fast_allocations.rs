@@ -0,0 +1,55 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use hashcash::{FormatVersion, Stamp, StampFormat, Target, DEFAULT_EXPIRY};
+
+// Counts the allocations of the current thread, so other test threads don't
+// disturb the count
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|allocations| allocations.set(allocations.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations_of(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+#[test]
+fn check_fast_allocates_only_the_payload() {
+    let formats = [StampFormat::default(), StampFormat::reference()];
+
+    for version in [FormatVersion::V0, FormatVersion::V1, FormatVersion::V2] {
+        for format in formats {
+            for target in [Target::BitPrefix, Target::NibblePrefix] {
+                let stamp = Stamp::new(version, 8, "foo@example.org".to_string(), None)
+                    .with_format(format)
+                    .with_target(target)
+                    .mint();
+
+                let allocations = allocations_of(|| {
+                    assert!(stamp
+                        .check_fast("foo@example.org", &DEFAULT_EXPIRY, 8)
+                        .is_ok());
+                });
+                assert_eq!(allocations, 1);
+            }
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use hashcash::{FormatVersion, Stamp, StampFormat};
+
+// Non-comment lines of a fixture file
+fn fixture(name: &str) -> Vec<String> {
+    let path = format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name);
+    std::fs::read_to_string(path)
+        .unwrap()
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+fn assert_verifies(line: &str) {
+    let stamp = Stamp::parse_with_format(line, StampFormat::reference())
+        .unwrap_or_else(|e| panic!("{}: {}", line, e));
+    assert_eq!(stamp.to_string(), line);
+
+    let resource = line.split(':').nth(3).unwrap();
+    if let Err(e) = stamp.check_no_expiry(resource, stamp.requested_bits()) {
+        panic!("{}: {}", line, e);
+    }
+}
+
+#[test]
+fn verifies_python_module_stamps() {
+    for line in fixture("python_hashcash.txt") {
+        assert_verifies(&line);
+    }
+}
+
+#[test]
+fn verifies_hashcash_m_output() {
+    for line in fixture("hashcash_m.txt") {
+        assert_verifies(&line);
+    }
+}
+
+// The tool prints `hashcash stamp: ` before the stamp, or a header with -X
+#[test]
+fn parses_hashcash_m_output_as_printed() {
+    for line in fixture("hashcash_m.txt") {
+        let resource = line.split(':').nth(3).unwrap();
+        for printed in [
+            format!("hashcash stamp: {}\n", line),
+            format!("X-Hashcash: {}\r\n", line),
+            format!("  {}\t", line),
+        ] {
+            let stamp = Stamp::parse_reference_output(&printed)
+                .unwrap_or_else(|e| panic!("{:?}: {}", printed, e));
+            assert_eq!(stamp.to_string(), line);
+            assert!(stamp
+                .check_no_expiry(resource, stamp.requested_bits())
+                .is_ok());
+        }
+    }
+}
+
+// Lines of the C tool's spend database, `<date> <resource> <stamp>`
+#[test]
+fn hashcash_db_lines_round_trip() {
+    for line in fixture("hashcash_m.txt") {
+        let stamp = Stamp::parse_with_format(&line, StampFormat::reference()).unwrap();
+        let db_line = stamp.to_hashcash_db_line();
+        let (date, resource) = (
+            line.split(':').nth(2).unwrap(),
+            line.split(':').nth(3).unwrap(),
+        );
+        assert_eq!(db_line, format!("{} {} {}", date, resource, line));
+
+        let read = Stamp::from_hashcash_db_line(&db_line, StampFormat::reference())
+            .unwrap_or_else(|e| panic!("{:?}: {}", db_line, e));
+        assert_eq!(read.to_string(), line);
+        assert!(read
+            .check_no_expiry(resource, read.requested_bits())
+            .is_ok());
+    }
+
+    let minted = Stamp::new(FormatVersion::V1, 8, "r".to_string(), None).mint();
+    let read = Stamp::from_hashcash_db_line(&minted.to_hashcash_db_line(), StampFormat::default());
+    assert!(read.unwrap() == minted);
+}
+
+// What the python module's check() verifies: seven fields and a SHA-1 hex digest
+// of the whole stamp starting with bits / 4 zeros
+#[test]
+fn emits_stamps_the_python_module_accepts() {
+    for bits in [8, 12, 16] {
+        let stamp = Stamp::new(FormatVersion::V1, bits, "foo@example.org".to_string(), None)
+            .with_format(StampFormat::reference())
+            .mint();
+        let line = stamp.to_string();
+
+        let fields: Vec<&str> = line.split(':').collect();
+        assert_eq!(fields.len(), 7);
+        assert_eq!(fields[2].len(), 6);
+
+        let digest = sha1_smol::Sha1::from(&line).digest().to_string();
+        assert!(digest.starts_with(&"0".repeat(usize::from(bits) / 4)));
+    }
+}
@@ -0,0 +1,71 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Barrier;
+use std::thread;
+
+use chrono::Duration;
+use hashcash::{
+    FormatVersion, HashcashError, MemorySpendStore, RingSpendStore, ShardedSpendStore, SpendStore,
+    Stamp, TtlSpendStore,
+};
+
+static THREADS: usize = 32;
+static ROUNDS: usize = 20;
+
+// Redeems each of a few stamps from every thread at once, exactly one redemption
+// per stamp may succeed and all others must see AlreadySpent
+fn assert_single_redemption(store: &(impl SpendStore + Sync)) {
+    assert_single(|stamp, expiry| stamp.redeem("foo@example.org", expiry, 8, store));
+}
+
+fn assert_single(redeem: impl Fn(&Stamp, &Duration) -> Result<(), HashcashError> + Sync) {
+    let expiry = Duration::days(1);
+
+    for _ in 0..ROUNDS {
+        let stamp = Stamp::new(FormatVersion::V1, 8, "foo@example.org".to_string(), None).mint();
+        let barrier = Barrier::new(THREADS);
+        let accepted = AtomicUsize::new(0);
+
+        thread::scope(|scope| {
+            for _ in 0..THREADS {
+                scope.spawn(|| {
+                    barrier.wait();
+                    match redeem(&stamp, &expiry) {
+                        Ok(()) => {
+                            accepted.fetch_add(1, Ordering::Relaxed);
+                        }
+                        Err(HashcashError::AlreadySpent) => {}
+                        Err(e) => panic!("unexpected rejection: {}", e),
+                    }
+                });
+            }
+        });
+
+        assert_eq!(accepted.load(Ordering::Relaxed), 1);
+    }
+}
+
+#[test]
+fn memory_store_redeems_once() {
+    assert_single_redemption(&MemorySpendStore::new());
+}
+
+#[test]
+fn verify_and_redeem_redeems_once() {
+    let store = MemorySpendStore::new();
+    assert_single(|stamp, expiry| stamp.verify_and_redeem("foo@example.org", expiry, 8, &store));
+}
+
+#[test]
+fn sharded_store_redeems_once() {
+    assert_single_redemption(&ShardedSpendStore::new(4));
+}
+
+#[test]
+fn ring_store_redeems_once() {
+    assert_single_redemption(&RingSpendStore::new(64));
+}
+
+#[test]
+fn ttl_store_redeems_once() {
+    assert_single_redemption(&TtlSpendStore::new(Duration::days(1)));
+}